@@ -51,7 +51,7 @@ fn pong_main() -> Result<()> {
     while !exit_requested.load(Ordering::Relaxed) && ecal::ok() {
         let start = Instant::now();
 
-        if let Some(ping) = subscriber.try_recv(tick_len) {
+        if let Ok(ping) = subscriber.try_recv(tick_len) {
             log::info!("Ping {}", ping.sync);
             pong.sync = ping.sync + 1;
             log::info!("Pong {}", pong.sync);
@@ -79,6 +79,10 @@ fn ping_main() -> Result<()> {
     let publisher = Publisher::<ecal_rs::Ping>::new("/kpns/demo/ping")?;
     let subscriber = Subscriber::<ecal_rs::Pong>::new("/kpns/demo/pong")?;
 
+    if !publisher.wait_for_subscribers(Duration::from_secs(10)) {
+        log::warn!("No pong is listening yet.");
+    }
+
     let mut ping = ecal_rs::Ping { sync: 1 };
 
     while !exit_requested.load(Ordering::Relaxed) && ecal::ok() {
@@ -87,7 +91,7 @@ fn ping_main() -> Result<()> {
         log::info!("Ping {}", ping.sync);
         publisher.send(&ping)?;
 
-        if let Some(pong) = subscriber.try_recv(tick_len) {
+        if let Ok(pong) = subscriber.try_recv(tick_len) {
             log::info!("Pong {}", pong.sync);
             ping.sync = pong.sync;
         }
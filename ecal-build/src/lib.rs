@@ -0,0 +1,193 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Generates eCAL services from protobuf `service` definitions.
+//!
+//! [`ServiceGenerator`] plugs into `prost_build` and turns every `service` block
+//! into a trait annotated with `#[ecal::service]`, which in turn provides the
+//! `<Service>Server` adapter and `<Service>Client` stub. Services, methods and
+//! their request and response types are registered under the same names eCAL's
+//! C++ protobuf services use, so Rust and C++ nodes can call each other.
+//!
+//! ```no_run
+//! let mut config = prost_build::Config::new();
+//! config.type_attribute(".", "#[derive(ecal::Message)]");
+//! config.service_generator(Box::new(ecal_build::ServiceGenerator::new()));
+//! config
+//!     .compile_protos(&["proto/math.proto"], &["proto/"])
+//!     .unwrap();
+//! ```
+//!
+//! The generated code expects the `ecal` crate with its `derive` feature, and
+//! `prost` for the messages.
+
+use prost_build::{Method, Service};
+
+/// A `prost_build::ServiceGenerator` emitting `#[ecal::service]` traits.
+#[derive(Debug, Clone)]
+pub struct ServiceGenerator {
+    format: String,
+}
+
+impl ServiceGenerator {
+    /// Creates a generator encoding payloads with `ecal::format::prost::Prost`.
+    pub fn new() -> Self {
+        ServiceGenerator {
+            format: "ecal::format::prost::Prost".to_owned(),
+        }
+    }
+
+    /// Sets the path of the `format` type passed to `#[ecal::service]`.
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = format.into();
+        self
+    }
+}
+
+impl Default for ServiceGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl prost_build::ServiceGenerator for ServiceGenerator {
+    fn generate(&mut self, service: Service, buf: &mut String) {
+        // eCAL's C++ `CServiceServer<T>` registers the full name of the service
+        // descriptor, including its package.
+        let full_name = if service.package.is_empty() {
+            service.proto_name.clone()
+        } else {
+            format!("{}.{}", service.package, service.proto_name)
+        };
+
+        buf.push_str(&format!(
+            "/// The name the `{}` service is registered under.\n",
+            full_name
+        ));
+        buf.push_str(&format!(
+            "pub const {}_SERVICE_NAME: &str = \"{}\";\n\n",
+            upper_snake_case(&service.name),
+            full_name
+        ));
+
+        service.comments.append_with_indent(0, buf);
+        buf.push_str(&format!("#[ecal::service(format = \"{}\")]\n", self.format));
+        buf.push_str(&format!("pub trait {} {{\n", service.name));
+        for method in &service.methods {
+            if method.client_streaming || method.server_streaming {
+                println!(
+                    "cargo:warning=Skipping streaming method {}.{}, eCAL services only support unary calls",
+                    full_name, method.proto_name
+                );
+                continue;
+            }
+            generate_method(method, buf);
+        }
+        buf.push_str("}\n");
+    }
+}
+
+fn generate_method(method: &Method, buf: &mut String) {
+    method.comments.append_with_indent(1, buf);
+    // The C++ side names methods as in the .proto file and their types after
+    // the message descriptor's name, without the package.
+    buf.push_str(&format!("    #[method_name = \"{}\"]\n", method.proto_name));
+    buf.push_str(&format!(
+        "    #[request_type = \"{}\"]\n",
+        message_name(&method.input_proto_type)
+    ));
+    buf.push_str(&format!(
+        "    #[response_type = \"{}\"]\n",
+        message_name(&method.output_proto_type)
+    ));
+    buf.push_str(&format!(
+        "    fn {}(&self, request: {}) -> ::core::result::Result<{}, ecal::ServiceError>;\n",
+        method.name, method.input_type, method.output_type
+    ));
+}
+
+/// Strips the package and enclosing messages from a fully qualified proto type.
+fn message_name(proto_type: &str) -> &str {
+    proto_type.rsplit('.').next().unwrap_or(proto_type)
+}
+
+fn upper_snake_case(name: &str) -> String {
+    let mut upper = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            upper.push('_');
+        }
+        upper.extend(c.to_uppercase());
+    }
+    upper
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServiceGenerator;
+    use prost_build::{Comments, Method, Service, ServiceGenerator as _};
+
+    fn method(name: &str, proto_name: &str, server_streaming: bool) -> Method {
+        Method {
+            name: name.to_owned(),
+            proto_name: proto_name.to_owned(),
+            comments: Comments::default(),
+            input_type: "AddRequest".to_owned(),
+            output_type: "AddResponse".to_owned(),
+            input_proto_type: ".math.AddRequest".to_owned(),
+            output_proto_type: ".math.AddResponse".to_owned(),
+            options: Default::default(),
+            client_streaming: false,
+            server_streaming,
+        }
+    }
+
+    #[test]
+    fn generates_ecal_service_trait() {
+        let service = Service {
+            name: "MathService".to_owned(),
+            proto_name: "MathService".to_owned(),
+            package: "math".to_owned(),
+            comments: Comments {
+                leading: vec![" Basic arithmetic.".to_owned()],
+                ..Comments::default()
+            },
+            methods: vec![
+                method("add", "Add", false),
+                method("add_all", "AddAll", true),
+            ],
+            options: Default::default(),
+        };
+
+        let mut buf = String::new();
+        ServiceGenerator::new().generate(service, &mut buf);
+
+        assert_eq!(
+            buf,
+            "/// The name the `math.MathService` service is registered under.\n\
+             pub const MATH_SERVICE_SERVICE_NAME: &str = \"math.MathService\";\n\
+             \n\
+             /// Basic arithmetic.\n\
+             #[ecal::service(format = \"ecal::format::prost::Prost\")]\n\
+             pub trait MathService {\n\
+             \x20   #[method_name = \"Add\"]\n\
+             \x20   #[request_type = \"AddRequest\"]\n\
+             \x20   #[response_type = \"AddResponse\"]\n\
+             \x20   fn add(&self, request: AddRequest) -> ::core::result::Result<AddResponse, ecal::ServiceError>;\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Compiles the code generated for a service with several methods.
+
+use ecal_build::ServiceGenerator;
+use prost_build::{Comments, Method, Service, ServiceGenerator as _};
+
+mod math {
+    #[derive(Clone, PartialEq, prost::Message, ecal::Message)]
+    pub struct BinaryRequest {
+        #[prost(double, tag = "1")]
+        pub a: f64,
+        #[prost(double, tag = "2")]
+        pub b: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message, ecal::Message)]
+    pub struct Result {
+        #[prost(double, tag = "1")]
+        pub value: f64,
+    }
+
+    // The generated code is kept next to this test, which checks it is up to date.
+    include!("generated/math_service.rs");
+}
+
+fn method(name: &str, proto_name: &str) -> Method {
+    Method {
+        name: name.to_owned(),
+        proto_name: proto_name.to_owned(),
+        comments: Comments::default(),
+        input_type: "BinaryRequest".to_owned(),
+        output_type: "Result".to_owned(),
+        input_proto_type: ".math.BinaryRequest".to_owned(),
+        output_proto_type: ".math.Result".to_owned(),
+        options: Default::default(),
+        client_streaming: false,
+        server_streaming: false,
+    }
+}
+
+#[test]
+fn generated_code_is_up_to_date() {
+    let service = Service {
+        name: "MathService".to_owned(),
+        proto_name: "MathService".to_owned(),
+        package: "math".to_owned(),
+        comments: Comments::default(),
+        methods: vec![method("add", "Add"), method("subtract", "Subtract")],
+        options: Default::default(),
+    };
+
+    let mut buf = String::new();
+    ServiceGenerator::new().generate(service, &mut buf);
+
+    let generated = include_str!("generated/math_service.rs");
+    let (_, generated) = generated.split_at(generated.find("\n\n").unwrap() + 2);
+    assert_eq!(buf, generated);
+}
+
+#[test]
+fn generated_service_implements_server_and_client() {
+    use math::{BinaryRequest, MathService, MathServiceClient, MathServiceServer, Result};
+
+    struct Math;
+
+    impl MathService for Math {
+        fn add(&self, request: BinaryRequest) -> std::result::Result<Result, ecal::ServiceError> {
+            Ok(Result {
+                value: request.a + request.b,
+            })
+        }
+
+        fn subtract(
+            &self,
+            request: BinaryRequest,
+        ) -> std::result::Result<Result, ecal::ServiceError> {
+            Ok(Result {
+                value: request.a - request.b,
+            })
+        }
+    }
+
+    let _server = MathServiceServer::new::<Math>;
+    let _subtract = MathServiceClient::subtract;
+    assert_eq!(math::MATH_SERVICE_SERVICE_NAME, "math.MathService");
+    let difference = Math.subtract(BinaryRequest { a: 3.0, b: 1.0 }).unwrap();
+    assert_eq!(difference.value, 2.0);
+}
@@ -0,0 +1,30 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+/// The name the `math.MathService` service is registered under.
+pub const MATH_SERVICE_SERVICE_NAME: &str = "math.MathService";
+
+#[ecal::service(format = "ecal::format::prost::Prost")]
+pub trait MathService {
+    #[method_name = "Add"]
+    #[request_type = "BinaryRequest"]
+    #[response_type = "Result"]
+    fn add(&self, request: BinaryRequest) -> ::core::result::Result<Result, ecal::ServiceError>;
+    #[method_name = "Subtract"]
+    #[request_type = "BinaryRequest"]
+    #[response_type = "Result"]
+    fn subtract(&self, request: BinaryRequest) -> ::core::result::Result<Result, ecal::ServiceError>;
+}
@@ -0,0 +1,55 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Checks that the monitoring messages of the `ecal` crate are generated from
+//! the bundled eCAL `.proto` files.
+//!
+//! This is the only test in this binary, as it points prost-build to the vendored
+//! `protoc` through the process environment.
+
+use std::{env, fs, path::Path};
+
+#[test]
+fn monitoring_pb_is_generated_from_proto() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let out = env::temp_dir().join(format!("ecal-pb-{}", std::process::id()));
+    fs::create_dir_all(&out).unwrap();
+    if env::var_os("PROTOC").is_none() {
+        env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+    }
+
+    let protos = [
+        "host",
+        "logging",
+        "monitoring",
+        "process",
+        "service",
+        "topic",
+    ]
+    .iter()
+    .map(|name| root.join(format!("proto/ecal/pb/{}.proto", name)))
+    .collect::<Vec<_>>();
+    prost_build::Config::new()
+        .out_dir(&out)
+        .compile_protos(&protos, &[root.join("proto")])
+        .unwrap();
+    let generated = fs::read_to_string(out.join("e_cal.pb.rs")).unwrap();
+    fs::remove_dir_all(&out).unwrap();
+
+    let checked_in = include_str!("../../src/monitoring/pb.rs");
+    let code = checked_in.split_once("differences.\n\n").unwrap().1;
+    assert_eq!(code, generated, "src/monitoring/pb.rs is out of date");
+}
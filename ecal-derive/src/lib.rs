@@ -13,12 +13,17 @@
  * 
  * SPDX-License-Identifier: Apache-2.0
  ********************************************************************************/
- 
+
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Attribute, DeriveInput, Lit, LitStr, Meta};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Attribute, AttributeArgs, DeriveInput, FnArg, GenericArgument, ItemTrait,
+    Lit, LitStr, Meta, NestedMeta, Path, PathArguments, ReturnType, TraitItem, TraitItemMethod,
+    Type,
+};
 
 #[proc_macro_derive(Message, attributes(type_name, type_prefix))]
 pub fn ecal_message_derive(input: TokenStream) -> TokenStream {
@@ -41,6 +46,280 @@ pub fn ecal_message_derive(input: TokenStream) -> TokenStream {
     proc_macro::TokenStream::from(expanded)
 }
 
+/// Generates an eCAL server adapter and client stub for a service trait.
+///
+/// Every trait method takes `&self` and a request and returns
+/// `Result<Response, ecal::ServiceError>`. For a trait `Calculator`, the macro
+/// generates `CalculatorServer`, offering an implementation of the trait on an
+/// [`ecal::Server`], and `CalculatorClient`, calling the methods on an
+/// [`ecal::Client`]. Requests and responses are encoded with the generic
+/// `format` type, `ecal::format::prost::Prost` by default, and methods are
+/// registered under their name unless renamed with `#[method_name = "..."]`.
+/// Requests and responses implement `ecal::Message`, whose `type_name()` is
+/// registered as the request and response type, matching what other eCAL clients
+/// and servers register. It can be overridden with `#[request_type = "..."]` and
+/// `#[response_type = "..."]`.
+///
+/// The generated code refers to the `ecal` crate as `ecal`. If it is renamed in
+/// `Cargo.toml` or re-exported by another crate, give its path as
+/// `crate = "..."`, which also becomes the prefix of the default format.
+///
+/// ```ignore
+/// #[ecal::service(format = "ecal::format::prost::Prost")]
+/// pub trait Calculator {
+///     #[method_name = "Add"]
+///     fn add(&self, request: AddRequest) -> Result<AddResponse, ecal::ServiceError>;
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn service(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let mut service = parse_macro_input!(input as ItemTrait);
+
+    match expand_service(&args, &mut service) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct ServiceMethod {
+    ident: Ident,
+    name: String,
+    request: Type,
+    response: Type,
+    /// Registered request and response type names, if not taken from `ecal::Message`.
+    types: Option<(String, String)>,
+}
+
+fn expand_service(args: &[NestedMeta], service: &mut ItemTrait) -> syn::Result<TokenStream2> {
+    let mut krate: Path = syn::parse_str("ecal")?;
+    let mut format: Option<Path> = None;
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("format") => {
+                format = Some(parse_path(&meta.lit)?);
+            }
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("crate") => {
+                krate = parse_path(&meta.lit)?;
+            }
+            arg => {
+                return Err(syn::Error::new_spanned(
+                    arg,
+                    "expected `format = \"...\"` or `crate = \"...\"`",
+                ))
+            }
+        }
+    }
+    let format = format.unwrap_or_else(|| syn::parse_quote!(#krate::format::prost::Prost));
+
+    let methods = service
+        .items
+        .iter_mut()
+        .filter_map(|item| match item {
+            TraitItem::Method(method) => Some(service_method(method)),
+            _ => None,
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let vis = &service.vis;
+    let trait_ident = &service.ident;
+    let server_ident = format_ident!("{}Server", trait_ident);
+    let client_ident = format_ident!("{}Client", trait_ident);
+
+    let add_methods = methods.iter().map(|method| {
+        let ServiceMethod {
+            ident,
+            name,
+            request,
+            response,
+            types,
+        } = method;
+        let generics = quote!(<#request, #response, #format<#request>, #format<#response>>);
+        let (request_type, response_type) = match types {
+            Some((request_type, response_type)) => (quote!(#request_type), quote!(#response_type)),
+            None => (
+                quote!(<#request as #krate::Message>::type_name()),
+                quote!(<#response as #krate::Message>::type_name()),
+            ),
+        };
+        quote! {
+            {
+                let service = service.clone();
+                server.add_method_with_types::#generics(
+                    #name,
+                    #request_type,
+                    #response_type,
+                    move |request| service.#ident(request),
+                )?;
+            }
+        }
+    });
+
+    let calls = methods.iter().map(|method| {
+        let ServiceMethod {
+            ident,
+            name,
+            request,
+            response,
+            ..
+        } = method;
+        quote! {
+            pub fn #ident(
+                &self,
+                request: &#request,
+                timeout: std::time::Duration,
+            ) -> #krate::Result<Vec<#krate::ServiceResponse<#response>>> {
+                self.client
+                    .call::<#request, #response, #format<#request>, #format<#response>>(
+                        #name, request, timeout,
+                    )
+            }
+        }
+    });
+
+    Ok(quote! {
+        #service
+
+        /// Offers an implementation of the service on an eCAL server.
+        #vis struct #server_ident {
+            server: #krate::Server,
+        }
+
+        impl #server_ident {
+            pub fn new<T>(service_name: &str, service: T) -> #krate::Result<Self>
+            where
+                T: #trait_ident + Send + Sync + 'static,
+            {
+                let server = #krate::Server::new(service_name)?;
+                let service = std::sync::Arc::new(service);
+                #(#add_methods)*
+                Ok(#server_ident { server })
+            }
+
+            pub fn server(&self) -> &#krate::Server {
+                &self.server
+            }
+        }
+
+        /// Calls the methods of the service through an eCAL client.
+        #vis struct #client_ident {
+            client: #krate::Client,
+        }
+
+        impl #client_ident {
+            pub fn new(service_name: &str) -> #krate::Result<Self> {
+                Ok(#client_ident {
+                    client: #krate::Client::new(service_name)?,
+                })
+            }
+
+            pub fn client(&self) -> &#krate::Client {
+                &self.client
+            }
+
+            pub fn client_mut(&mut self) -> &mut #krate::Client {
+                &mut self.client
+            }
+
+            #(#calls)*
+        }
+    })
+}
+
+fn parse_path(lit: &Lit) -> syn::Result<Path> {
+    match lit {
+        Lit::Str(lit) => lit.parse(),
+        lit => Err(syn::Error::new_spanned(lit, "expected a string")),
+    }
+}
+
+/// Checks the signature of a service method and takes its `#[method_name]`,
+/// `#[request_type]` and `#[response_type]` attributes.
+fn service_method(method: &mut TraitItemMethod) -> syn::Result<ServiceMethod> {
+    let name = find_str_attr(&method.attrs, "method_name")?
+        .unwrap_or_else(|| method.sig.ident.to_string());
+    let request_type = find_str_attr(&method.attrs, "request_type")?;
+    let response_type = find_str_attr(&method.attrs, "response_type")?;
+    let types = match (request_type, response_type) {
+        (Some(request_type), Some(response_type)) => Some((request_type, response_type)),
+        (None, None) => None,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &method.sig,
+                "`#[request_type]` and `#[response_type]` must be given together",
+            ))
+        }
+    };
+    method.attrs.retain(|attr| {
+        !["method_name", "request_type", "response_type"]
+            .iter()
+            .any(|name| attr.path.is_ident(name))
+    });
+
+    let sig = &method.sig;
+    let mut inputs = sig.inputs.iter();
+    let request = match (inputs.next(), inputs.next(), inputs.next()) {
+        (Some(FnArg::Receiver(receiver)), Some(FnArg::Typed(request)), None)
+            if receiver.reference.is_some() && receiver.mutability.is_none() =>
+        {
+            (*request.ty).clone()
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "service methods take `&self` and a request",
+            ))
+        }
+    };
+
+    let response = match &sig.output {
+        ReturnType::Type(_, ty) => result_ok_type(ty),
+        ReturnType::Default => None,
+    }
+    .ok_or_else(|| {
+        syn::Error::new_spanned(
+            &sig.output,
+            "service methods return `Result<Response, ecal::ServiceError>`",
+        )
+    })?;
+
+    Ok(ServiceMethod {
+        ident: sig.ident.clone(),
+        name,
+        request,
+        response,
+        types,
+    })
+}
+
+fn result_ok_type(ty: &Type) -> Option<Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn find_str_attr(attrs: &[Attribute], name: &str) -> syn::Result<Option<String>> {
+    match attrs.iter().find(|attr| attr.path.is_ident(name)) {
+        Some(attr) => extract_str_lit(attr)
+            .map(|inner| Some(inner.value()))
+            .ok_or_else(|| {
+                syn::Error::new_spanned(attr, format!("Please use #[{} = \"...\"] attribute", name))
+            }),
+        None => Ok(None),
+    }
+}
+
 fn find_type_name(attrs: &[Attribute]) -> Option<String> {
     for attr in attrs.iter().filter(|attr| attr.path.is_ident("type_name")) {
         if let Some(inner) = extract_str_lit(attr) {
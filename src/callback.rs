@@ -0,0 +1,275 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+pub(crate) type Listener<E> = Box<dyn FnMut(&E) + Send>;
+
+/// Rust closures listening to a single eCAL callback slot.
+///
+/// eCAL keeps one C callback per handle and event type, so the registry installs
+/// one trampoline when the first listener arrives, fans every call out to all
+/// listeners and uninstalls the trampoline when the last listener is removed.
+pub(crate) struct Registry<E> {
+    /// Serializes installing and uninstalling the trampoline. It is never taken
+    /// while dispatching, so eCAL is free to hold its own callback lock while
+    /// calling into [`Registry::dispatch`].
+    registration: Mutex<()>,
+    listeners: Mutex<Listeners<E>>,
+}
+
+struct Listeners<E> {
+    next_id: u64,
+    entries: Vec<(u64, Listener<E>)>,
+}
+
+impl<E> Registry<E> {
+    pub fn new() -> Self {
+        Registry {
+            registration: Mutex::new(()),
+            listeners: Mutex::new(Listeners {
+                next_id: 0,
+                entries: Vec::new(),
+            }),
+        }
+    }
+
+    /// Adds a listener and returns its id.
+    ///
+    /// `install` is called if this is the first listener and must return `false`
+    /// if eCAL refused the trampoline, in which case the listener is dropped again.
+    pub fn insert(&self, listener: Listener<E>, install: impl FnOnce() -> bool) -> Option<u64> {
+        let _registration = lock(&self.registration);
+        let (id, first) = {
+            let mut listeners = lock(&self.listeners);
+            let id = listeners.next_id;
+            listeners.next_id += 1;
+            listeners.entries.push((id, listener));
+            (id, listeners.entries.len() == 1)
+        };
+
+        if first && !install() {
+            lock(&self.listeners).entries.clear();
+            return None;
+        }
+        Some(id)
+    }
+
+    /// Removes and frees a listener, calling `uninstall` if it was the last one.
+    pub fn remove(&self, id: u64, uninstall: impl FnOnce()) {
+        let _registration = lock(&self.registration);
+        let (removed, empty) = {
+            let mut listeners = lock(&self.listeners);
+            let removed = listeners
+                .entries
+                .iter()
+                .position(|(entry_id, _)| *entry_id == id)
+                .map(|index| listeners.entries.remove(index));
+            (removed, listeners.entries.is_empty())
+        };
+
+        if removed.is_some() && empty {
+            uninstall();
+        }
+    }
+
+    /// Calls every listener with `event`.
+    ///
+    /// Listeners run with the registry locked, so they must not add or remove
+    /// listeners of the same registry.
+    pub fn dispatch(&self, event: &E) {
+        for (_, listener) in lock(&self.listeners).entries.iter_mut() {
+            listener(event);
+        }
+    }
+}
+
+/// Adds `listener` to the registry of `handle` and returns a guard removing it again.
+///
+/// The guard only keeps a weak reference, so it neither keeps the eCAL handle alive
+/// nor touches it after it was destroyed.
+pub(crate) fn listen<H, E>(
+    handle: &Arc<H>,
+    registry: fn(&H) -> &Registry<E>,
+    install: fn(&H) -> bool,
+    uninstall: fn(&H),
+    listener: Listener<E>,
+) -> Option<CallbackGuard>
+where
+    H: Send + Sync + 'static,
+    E: 'static,
+{
+    let id = registry(handle).insert(listener, || install(handle))?;
+
+    let handle = Arc::downgrade(handle);
+    Some(CallbackGuard::new(move || {
+        if let Some(handle) = handle.upgrade() {
+            registry(&handle).remove(id, || uninstall(&handle));
+        }
+    }))
+}
+
+/// Runs the Rust side of a C callback, logging a panic instead of unwinding into eCAL.
+///
+/// Returns `None` if `f` panicked.
+pub(crate) fn catch_unwind<R>(callback: &str, f: impl FnOnce() -> R) -> Option<R> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .map_err(|payload| log::error!("{} panicked: {}", callback, panic_message(&*payload)))
+        .ok()
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("<non-string payload>", String::as_str),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Keeps a callback registered with eCAL.
+///
+/// Dropping the guard unregisters the callback and frees the closure. A guard
+/// may outlive the publisher or subscriber it was created from, in which case
+/// the closure was already freed together with it and dropping does nothing.
+#[must_use = "the callback is unregistered as soon as the guard is dropped"]
+pub struct CallbackGuard {
+    remove: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl CallbackGuard {
+    pub(crate) fn new(remove: impl FnOnce() + Send + 'static) -> Self {
+        CallbackGuard {
+            remove: Some(Box::new(remove)),
+        }
+    }
+
+    /// Keeps the callback registered for the whole lifetime of its publisher or subscriber.
+    pub fn forget(mut self) {
+        self.remove = None;
+    }
+}
+
+impl Drop for CallbackGuard {
+    fn drop(&mut self) {
+        if let Some(remove) = self.remove.take() {
+            remove();
+        }
+    }
+}
+
+impl std::fmt::Debug for CallbackGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackGuard").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{catch_unwind, listen, Registry};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    struct Handle {
+        installed: AtomicUsize,
+        registry: Registry<usize>,
+    }
+
+    fn install(handle: &Handle) -> bool {
+        handle.installed.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    fn uninstall(handle: &Handle) {
+        handle.installed.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn listeners_share_one_trampoline() {
+        let handle = Arc::new(Handle {
+            installed: AtomicUsize::new(0),
+            registry: Registry::new(),
+        });
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let listener = |calls: Arc<AtomicUsize>| {
+            Box::new(move |value: &usize| {
+                calls.fetch_add(*value, Ordering::SeqCst);
+            })
+        };
+        let first = listen(
+            &handle,
+            |h| &h.registry,
+            install,
+            uninstall,
+            listener(calls.clone()),
+        );
+        let second = listen(
+            &handle,
+            |h| &h.registry,
+            install,
+            uninstall,
+            listener(calls.clone()),
+        );
+        assert_eq!(handle.installed.load(Ordering::SeqCst), 1);
+
+        handle.registry.dispatch(&1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        drop(first);
+        handle.registry.dispatch(&1);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(handle.installed.load(Ordering::SeqCst), 1);
+
+        drop(second);
+        assert_eq!(handle.installed.load(Ordering::SeqCst), 0);
+        // Both closures were freed, only the test still holds the counter.
+        assert_eq!(Arc::strong_count(&calls), 1);
+    }
+
+    #[test]
+    fn guard_outliving_handle_is_harmless() {
+        let handle = Arc::new(Handle {
+            installed: AtomicUsize::new(0),
+            registry: Registry::new(),
+        });
+        let guard = listen(
+            &handle,
+            |h| &h.registry,
+            install,
+            uninstall,
+            Box::new(|_| {}),
+        );
+        drop(handle);
+        drop(guard);
+    }
+
+    #[test]
+    fn panics_are_caught() {
+        assert_eq!(catch_unwind("test callback", || 1), Some(1));
+        assert_eq!(catch_unwind("test callback", || panic!("boom")), None::<()>);
+    }
+}
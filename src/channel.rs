@@ -0,0 +1,405 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Delivery of received messages through a bounded queue, see [`Subscriber::into_channel`].
+//!
+//! [`Subscriber::into_channel`]: crate::Subscriber::into_channel
+
+use crate::{CallbackGuard, RawSubscriber, Received, RecvError};
+use std::{
+    collections::VecDeque,
+    num::NonZeroUsize,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak},
+    time::{Duration, Instant},
+};
+
+/// What happens to a message arriving while the queue is full.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Drops the oldest queued message to make room.
+    DropOldest,
+    /// Drops the arriving message.
+    DropNewest,
+    /// Blocks eCAL's receive thread until there is room.
+    Block,
+}
+
+/// Queues of all open channels and streams, closed when eCAL is finalized.
+pub(crate) static OPEN: Open = Open::new();
+
+/// Ends all open channels and streams once their queued messages were taken.
+pub(crate) fn close_all() {
+    OPEN.close_all();
+}
+
+pub(crate) trait Close: Send + Sync {
+    /// Stops accepting items and releases a blocked push.
+    fn close(&self);
+}
+
+/// Registry of queues to close together, see [`OPEN`].
+pub(crate) struct Open(Mutex<Vec<Weak<dyn Close>>>);
+
+impl Open {
+    pub const fn new() -> Self {
+        Open(Mutex::new(Vec::new()))
+    }
+
+    /// Closes `queue` on [`close_all`](Self::close_all), for as long as it is alive.
+    pub fn register<Q: Close + 'static>(&self, queue: &Arc<Q>) {
+        let mut open = self.lock();
+        open.retain(|queue| queue.strong_count() > 0);
+        open.push(Arc::downgrade(queue) as Weak<dyn Close>);
+    }
+
+    pub fn close_all(&self) {
+        let open = std::mem::take(&mut *self.lock());
+        for queue in open.iter().filter_map(Weak::upgrade) {
+            queue.close();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Weak<dyn Close>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub(crate) struct Queue<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: DropPolicy,
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    dropped: u64,
+    closed: bool,
+}
+
+impl<T> Queue<T> {
+    pub fn new(capacity: NonZeroUsize, policy: DropPolicy) -> Self {
+        let capacity = capacity.get();
+        Queue {
+            state: Mutex::new(State {
+                items: VecDeque::with_capacity(capacity),
+                dropped: 0,
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+            policy,
+        }
+    }
+
+    pub fn push(&self, item: T) {
+        let mut state = self.lock();
+        while state.items.len() >= self.capacity && !state.closed {
+            match self.policy {
+                DropPolicy::DropOldest => {
+                    state.items.pop_front();
+                    state.dropped += 1;
+                }
+                DropPolicy::DropNewest => {
+                    state.dropped += 1;
+                    return;
+                }
+                DropPolicy::Block => {
+                    state = self
+                        .not_full
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+        if state.closed {
+            return;
+        }
+        state.items.push_back(item);
+        self.not_empty.notify_one();
+    }
+
+    /// Pops the oldest item, waiting until `deadline` if the queue is empty.
+    /// Returns `None` right away once the queue is closed and empty.
+    pub fn pop(&self, deadline: Option<Instant>) -> Option<T> {
+        let mut state = self.lock();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.not_full.notify_one();
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            let timeout = match deadline {
+                Some(deadline) => deadline.checked_duration_since(Instant::now())?,
+                None => return None,
+            };
+            state = self
+                .not_empty
+                .wait_timeout(state, timeout)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Pops the oldest item, waiting for one for as long as the queue is open.
+    pub fn pop_wait(&self) -> Option<T> {
+        let mut state = self.lock();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.not_full.notify_one();
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .not_empty
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Stops accepting items and releases a blocked [`push`](Self::push) or
+    /// [`pop`](Self::pop), which then only return the queued items.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_full.notify_all();
+        self.not_empty.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Send> Close for Queue<T> {
+    fn close(&self) {
+        Queue::close(self);
+    }
+}
+
+/// Receiving end of a subscriber turned into a channel.
+///
+/// Owns the subscriber, which stops receiving when the receiver is dropped. The
+/// queue is closed when eCAL is finalized, releasing a receive callback blocked
+/// on it.
+pub struct Receiver<T> {
+    queue: Arc<Queue<Received<T>>>,
+    _guard: CallbackGuard,
+    _subscriber: RawSubscriber,
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(
+        queue: Arc<Queue<Received<T>>>,
+        guard: CallbackGuard,
+        subscriber: RawSubscriber,
+    ) -> Self
+    where
+        T: Send + 'static,
+    {
+        OPEN.register(&queue);
+        Receiver {
+            queue,
+            _guard: guard,
+            _subscriber: subscriber,
+        }
+    }
+
+    /// Blocks until a message arrives, failing with [`RecvError::Closed`] once eCAL
+    /// was finalized and the queue is drained.
+    pub fn recv(&self) -> Result<Received<T>, RecvError> {
+        self.queue.pop_wait().ok_or(RecvError::Closed)
+    }
+
+    /// Waits at most `timeout` for a message, failing with [`RecvError::Timeout`],
+    /// or with [`RecvError::Closed`] once eCAL was finalized and the queue is drained.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Received<T>, RecvError> {
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return self.recv(),
+        };
+        match self.queue.pop(Some(deadline)) {
+            Some(item) => Ok(item),
+            None if self.queue.is_closed() => Err(RecvError::Closed),
+            None => Err(RecvError::Timeout),
+        }
+    }
+
+    /// Returns a queued message without waiting.
+    pub fn try_recv(&self) -> Option<Received<T>> {
+        self.queue.pop(None)
+    }
+
+    /// Number of messages waiting in the queue.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of messages dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // Releases a callback blocked on a full queue, so the guard can unregister it.
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{DropPolicy, Open, Queue};
+    use std::{
+        num::NonZeroUsize,
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn queue(capacity: usize, policy: DropPolicy) -> Queue<u32> {
+        Queue::new(NonZeroUsize::new(capacity).unwrap(), policy)
+    }
+
+    fn drain(queue: &Queue<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| queue.pop(None)).collect()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_latest() {
+        let queue = queue(2, DropPolicy::DropOldest);
+        (1..=4).for_each(|item| queue.push(item));
+        assert_eq!(drain(&queue), vec![3, 4]);
+        assert_eq!(queue.dropped(), 2);
+    }
+
+    #[test]
+    fn drop_newest_keeps_earliest() {
+        let queue = queue(2, DropPolicy::DropNewest);
+        (1..=4).for_each(|item| queue.push(item));
+        assert_eq!(drain(&queue), vec![1, 2]);
+        assert_eq!(queue.dropped(), 2);
+    }
+
+    #[test]
+    fn block_waits_for_room() {
+        let queue = Arc::new(queue(1, DropPolicy::Block));
+        queue.push(1);
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push(2))
+        };
+        let deadline = Some(Instant::now() + Duration::from_secs(5));
+        assert_eq!(queue.pop(deadline), Some(1));
+        assert_eq!(queue.pop(deadline), Some(2));
+        producer.join().unwrap();
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[test]
+    fn close_releases_blocked_push() {
+        let queue = Arc::new(queue(1, DropPolicy::Block));
+        queue.push(1);
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push(2))
+        };
+        queue.close();
+        producer.join().unwrap();
+        assert_eq!(drain(&queue), vec![1]);
+    }
+
+    #[test]
+    fn close_releases_blocked_pop() {
+        let queue = Arc::new(queue(2, DropPolicy::Block));
+        let consumer = {
+            let queue = queue.clone();
+            thread::spawn(move || (queue.pop_wait(), queue.pop(None)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        queue.close();
+        assert_eq!(consumer.join().unwrap(), (None, None));
+    }
+
+    #[test]
+    fn closed_queue_hands_out_queued_items() {
+        let queue = queue(2, DropPolicy::Block);
+        queue.push(1);
+        queue.close();
+        queue.push(2);
+        let deadline = Some(Instant::now() + Duration::from_secs(5));
+        assert_eq!(queue.pop(deadline), Some(1));
+        assert_eq!(queue.pop(deadline), None);
+        assert_eq!(queue.pop_wait(), None);
+    }
+
+    #[test]
+    fn finalize_releases_blocked_push() {
+        // A registry of its own, closing `OPEN` would end the queues of other tests.
+        let open = Open::new();
+        let queue = Arc::new(queue(1, DropPolicy::Block));
+        open.register(&queue);
+        queue.push(1);
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.push(2))
+        };
+        open.close_all();
+        producer.join().unwrap();
+        assert_eq!(drain(&queue), vec![1]);
+    }
+
+    #[test]
+    fn queues_are_registered_until_closed() {
+        // `finalize()` in other tests may close `OPEN` at any time.
+        let open = Open::new();
+        let queue = Arc::new(queue(1, DropPolicy::Block));
+        open.register(&queue);
+        assert!(registered(&open, &queue));
+        open.close_all();
+        assert!(!registered(&open, &queue));
+        assert!(queue.is_closed());
+    }
+
+    /// Whether `queue` is closed by `open`.
+    pub(crate) fn registered<Q>(open: &Open, queue: &Arc<Q>) -> bool {
+        open.lock()
+            .iter()
+            .any(|open| open.as_ptr() as *const () == Arc::as_ptr(queue) as *const ())
+    }
+}
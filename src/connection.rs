@@ -0,0 +1,257 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+use std::{
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "async")]
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+type Probe = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// Number of peers connected to a publisher or subscriber, kept up to date from
+/// its connect and disconnect events.
+pub(crate) struct Connections {
+    state: Mutex<State>,
+    changed: Condvar,
+    /// Asks eCAL whether a peer is connected, which catches peers that connected
+    /// before the event listener was registered.
+    probe: Probe,
+}
+
+struct State {
+    count: usize,
+    #[cfg(feature = "async")]
+    next_waiter: u64,
+    #[cfg(feature = "async")]
+    wakers: HashMap<u64, Waker>,
+}
+
+impl Connections {
+    pub fn new(probe: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        Connections {
+            state: Mutex::new(State {
+                count: 0,
+                #[cfg(feature = "async")]
+                next_waiter: 0,
+                #[cfg(feature = "async")]
+                wakers: HashMap::new(),
+            }),
+            changed: Condvar::new(),
+            probe: Box::new(probe),
+        }
+    }
+
+    pub fn connected(&self) {
+        self.update(|count| count + 1);
+    }
+
+    pub fn disconnected(&self) {
+        self.update(|count| count.saturating_sub(1));
+    }
+
+    fn update(&self, f: impl FnOnce(usize) -> usize) {
+        let mut state = self.lock();
+        state.count = f(state.count);
+        self.changed.notify_all();
+        #[cfg(feature = "async")]
+        state.wakers.drain().for_each(|(_, waker)| waker.wake());
+    }
+
+    /// Blocks until at least one peer is connected or `timeout` elapsed.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        // A timeout too large for an `Instant` waits without a deadline.
+        let deadline = Instant::now().checked_add(timeout);
+        // The probe calls into eCAL, which may hold its own locks while sending
+        // events, so it runs before taking the state lock. Peers connecting after
+        // it ran are counted by the event listener.
+        if (self.probe)() {
+            return true;
+        }
+        let mut state = self.lock();
+        while state.count == 0 {
+            state = match deadline {
+                Some(deadline) => {
+                    let remaining = match deadline.checked_duration_since(Instant::now()) {
+                        Some(remaining) => remaining,
+                        None => return false,
+                    };
+                    self.changed
+                        .wait_timeout(state, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+        true
+    }
+
+    #[cfg(feature = "async")]
+    fn waiter(&self) -> u64 {
+        let mut state = self.lock();
+        state.next_waiter += 1;
+        state.next_waiter
+    }
+
+    #[cfg(feature = "async")]
+    fn poll_connected(&self, waiter: u64, probed: bool, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.lock();
+        if probed || state.count > 0 {
+            state.wakers.remove(&waiter);
+            Poll::Ready(())
+        } else {
+            match state.wakers.get_mut(&waiter) {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => {
+                    state.wakers.insert(waiter, cx.waker().clone());
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn forget_waiter(&self, waiter: u64) {
+        self.lock().wakers.remove(&waiter);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Future resolving to `true` once a peer is connected, or to `false` on timeout.
+#[cfg(feature = "async")]
+pub struct WaitForConnection {
+    connections: Arc<Connections>,
+    waiter: u64,
+    probed: bool,
+    delay: futures_timer::Delay,
+}
+
+#[cfg(feature = "async")]
+impl WaitForConnection {
+    pub(crate) fn new(connections: Arc<Connections>, timeout: Duration) -> Self {
+        WaitForConnection {
+            waiter: connections.waiter(),
+            connections,
+            probed: false,
+            delay: futures_timer::Delay::new(timeout),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Future for WaitForConnection {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        // Only peers connected before the first poll can have been missed.
+        let probed = !self.probed && (self.connections.probe)();
+        self.probed = true;
+        if self
+            .connections
+            .poll_connected(self.waiter, probed, cx)
+            .is_ready()
+        {
+            return Poll::Ready(true);
+        }
+        Pin::new(&mut self.delay).poll(cx).map(|()| false)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for WaitForConnection {
+    fn drop(&mut self) {
+        self.connections.forget_waiter(self.waiter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Connections;
+    use std::{sync::Arc, thread, time::Duration};
+
+    #[test]
+    fn wait_returns_once_connected() {
+        let connections = Arc::new(Connections::new(|| false));
+        assert!(!connections.wait(Duration::from_millis(10)));
+
+        let peer = {
+            let connections = connections.clone();
+            thread::spawn(move || connections.connected())
+        };
+        assert!(connections.wait(Duration::from_secs(5)));
+        peer.join().unwrap();
+
+        connections.disconnected();
+        connections.disconnected();
+        assert!(!connections.wait(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn wait_without_deadline_for_huge_timeouts() {
+        let connections = Arc::new(Connections::new(|| false));
+        let peer = {
+            let connections = connections.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                connections.connected();
+            })
+        };
+        assert!(connections.wait(Duration::MAX));
+        peer.join().unwrap();
+    }
+
+    #[test]
+    fn wait_asks_ecal_for_missed_peers() {
+        let connections = Connections::new(|| true);
+        assert!(connections.wait(Duration::from_millis(0)));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn repeated_polls_keep_one_waker() {
+        use super::WaitForConnection;
+        use futures::{future::FutureExt, task::noop_waker_ref};
+        use std::task::Context;
+
+        let connections = Arc::new(Connections::new(|| false));
+        let mut wait = WaitForConnection::new(connections.clone(), Duration::from_secs(60));
+        let mut cx = Context::from_waker(noop_waker_ref());
+        for _ in 0..3 {
+            assert!(wait.poll_unpin(&mut cx).is_pending());
+        }
+        assert_eq!(connections.lock().wakers.len(), 1);
+
+        connections.connected();
+        assert!(wait.poll_unpin(&mut cx).is_ready());
+        drop(wait);
+        assert!(connections.lock().wakers.is_empty());
+    }
+}
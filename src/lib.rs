@@ -14,25 +14,53 @@
  * SPDX-License-Identifier: Apache-2.0
  ********************************************************************************/
 
-use anyhow::Result;
 use std::{
+    convert::TryFrom,
     env, ffi,
     marker::PhantomData,
+    num::NonZeroUsize,
+    ops::{Deref, DerefMut},
     os::raw::{c_char, c_int, c_long, c_longlong, c_void},
     ptr, slice,
-    time::{Duration, Instant},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 use thiserror::Error;
 
-#[cfg(feature = "derive")]
-pub use ecal_derive::Message;
+pub use callback::CallbackGuard;
+use callback::Registry;
+use connection::Connections;
+#[cfg(feature = "async")]
+pub use connection::WaitForConnection;
+#[cfg(feature = "async")]
+pub use service::ResponseFuture;
+pub use service::{
+    CallState, Client, PendingCall, Server, ServiceError, ServiceInfo, ServiceResponse,
+};
+use type_check::TypeChecker;
+pub use type_check::{TypeCheck, TypeMismatch};
 
+#[cfg(feature = "derive")]
+pub use ecal_derive::{service, Message};
+
+mod callback;
+pub mod channel;
+mod connection;
+pub mod logging;
+#[cfg(feature = "monitoring")]
+pub mod monitoring;
+mod service;
+#[cfg(feature = "async")]
+pub mod stream;
 pub mod sys;
+mod type_check;
 
 pub trait Message {
     fn type_name() -> &'static str;
 }
 
+pub type Result<T, E = CalError> = std::result::Result<T, E>;
+
 // TODO: ... yeah
 #[derive(Debug, Error)]
 pub enum CalError {
@@ -42,18 +70,66 @@ pub enum CalError {
     PublisherCreationFailed(String),
     #[error("Unable to create new subscriber for `{0}`")]
     SubscriberCreationFailed(String),
+    #[error("Unable to create new server for `{0}`")]
+    ServerCreationFailed(String),
+    #[error("Unable to create new client for `{0}`")]
+    ClientCreationFailed(String),
     #[error("The message was not sent or was only partially sent.")]
     PublishFailed,
-    #[error("Unexpected message format")]
-    InvalidFormat,
+    #[error("Unable to encode the message.")]
+    Encode(#[source] format::Error),
+    #[error("eCAL rejected the `{0}` setting.")]
+    SettingRejected(&'static str),
+    #[error("The queue capacity must not be zero.")]
+    ZeroCapacity,
+    #[error("Calling service method `{0}` failed and no server answered.")]
+    CallFailed(String),
+    #[error("eCAL refused to register the callback.")]
+    CallbackRegistrationFailed,
+    #[error("String passed to eCAL contains a nul byte.")]
+    InvalidString(#[from] ffi::NulError),
+    #[error(transparent)]
+    Recv(#[from] RecvError),
+    #[error("eCAL monitoring is not available, was eCAL initialized with monitoring?")]
+    MonitoringUnavailable,
+    #[error("Unable to decode eCAL monitoring information.")]
+    MonitoringDecode(#[source] format::Error),
+    #[error("eCAL rejected the `{setting}` setting and then the `{restoring}` setting while restoring the previous filter, the filter in use is unknown.")]
+    FilterRestoreFailed {
+        setting: &'static str,
+        restoring: &'static str,
+    },
+    #[error("Invalid monitoring filter `{pattern}`.")]
+    InvalidFilter {
+        pattern: String,
+        #[source]
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Why no message was received.
+#[derive(Debug, Error)]
+pub enum RecvError {
     #[error("Time-out waiting to receive message.")]
     Timeout,
+    #[error("Unable to decode a message of {len} bytes.")]
+    Decode {
+        #[source]
+        error: format::Error,
+        /// Size of the raw payload in bytes.
+        len: usize,
+    },
+    #[error("eCAL was finalized.")]
+    Closed,
+    /// The payload was refused, see [`TypeCheck::Refuse`].
     #[error(transparent)]
-    Unknown(#[from] anyhow::Error),
+    TypeMismatch(TypeMismatch),
 }
 
 pub mod format {
-    use anyhow::Result;
+    /// Error reported by a serializer or deserializer.
+    pub type Error = Box<dyn std::error::Error + Send + Sync>;
+    pub type Result<T, E = Error> = std::result::Result<T, E>;
 
     pub trait Format {
         fn topic_type() -> String;
@@ -62,6 +138,13 @@ pub mod format {
 
     pub trait Serializer<T> {
         fn serialize(message: &T, buffer: &mut Vec<u8>) -> Result<()>;
+
+        /// Number of bytes [`serialize`](Self::serialize) is going to append, if cheaply known.
+        ///
+        /// Publishers use it to grow their send buffer once instead of while encoding.
+        fn encoded_len(_message: &T) -> Option<usize> {
+            None
+        }
     }
 
     pub trait Deserializer<'a, T> {
@@ -70,8 +153,7 @@ pub mod format {
 
     #[cfg(feature = "use_msgpack")]
     pub mod msgpack {
-        use super::{Deserializer, Format, Serializer};
-        use anyhow::{Error, Result};
+        use super::{Deserializer, Error, Format, Result, Serializer};
         use serde::{Deserialize, Serialize};
         use std::marker::PhantomData;
 
@@ -114,9 +196,8 @@ pub mod format {
 
     #[cfg(feature = "use_prost")]
     pub mod prost {
-        use super::{Deserializer, Format, Serializer};
+        use super::{Deserializer, Error, Format, Result, Serializer};
         pub use ::prost::Message as ProstMessage;
-        use anyhow::{Error, Result};
         use std::marker::PhantomData;
 
         pub struct Prost<T: crate::Message + ::prost::Message> {
@@ -144,6 +225,10 @@ pub mod format {
             fn serialize(message: &T, buf: &mut Vec<u8>) -> Result<()> {
                 message.encode(buf).map_err(Error::from)
             }
+
+            fn encoded_len(message: &T) -> Option<usize> {
+                Some(message.encoded_len())
+            }
         }
 
         impl<'a, T> Deserializer<'a, T> for Prost<T>
@@ -158,8 +243,7 @@ pub mod format {
 
     #[cfg(feature = "use_protobuf")]
     pub mod protobuf {
-        use super::{Deserializer, Format, Serializer};
-        use anyhow::{Error, Result};
+        use super::{Deserializer, Error, Format, Result, Serializer};
         use std::marker::PhantomData;
 
         pub struct Protobuf<T: ::protobuf::Message> {
@@ -190,6 +274,10 @@ pub mod format {
             fn serialize(message: &T, buf: &mut Vec<u8>) -> Result<()> {
                 message.write_to_vec(buf).map_err(Error::from)
             }
+
+            fn encoded_len(message: &T) -> Option<usize> {
+                Some(message.compute_size() as usize)
+            }
         }
 
         impl<'a, T> Deserializer<'a, T> for Protobuf<T>
@@ -204,13 +292,14 @@ pub mod format {
 
     #[cfg(feature = "use_capnp")]
     pub mod capnp {
-        use anyhow::Result;
         use std::marker::PhantomData;
 
-        use super::{Deserializer, Format, Serializer};
+        use super::{Deserializer, Format, Result, Serializer};
         use capnp::{
             message::{ReaderOptions, TypedBuilder, TypedReader},
-            serialize::{read_message_from_flat_slice, write_message_to_words, SliceSegments},
+            serialize::{
+                compute_serialized_size_in_words, read_message, write_message, OwnedSegments,
+            },
             traits::Owned,
         };
 
@@ -239,17 +328,22 @@ pub mod format {
             T: crate::Message + Owned,
         {
             fn serialize(message: &TypedBuilder<T>, buffer: &mut Vec<u8>) -> Result<()> {
-                buffer.append(&mut write_message_to_words(message.borrow_inner()));
+                write_message(buffer, message.borrow_inner())?;
                 Ok(())
             }
+
+            fn encoded_len(message: &TypedBuilder<T>) -> Option<usize> {
+                Some(compute_serialized_size_in_words(message.borrow_inner()) * 8)
+            }
         }
 
-        impl<'a, T> Deserializer<'a, TypedReader<SliceSegments<'a>, T>> for Capnp<T>
+        /// Copies the segments out of the buffer, which eCAL frees after receiving.
+        impl<'a, T> Deserializer<'a, TypedReader<OwnedSegments, T>> for Capnp<T>
         where
             T: crate::Message + Owned,
         {
-            fn deserialize(mut buffer: &'a [u8]) -> Result<TypedReader<SliceSegments<'a>, T>> {
-                Ok(read_message_from_flat_slice(&mut buffer, ReaderOptions::default())?.into())
+            fn deserialize(buffer: &'a [u8]) -> Result<TypedReader<OwnedSegments, T>> {
+                Ok(read_message(buffer, ReaderOptions::default())?.into())
             }
         }
     }
@@ -280,367 +374,1709 @@ pub mod protobuf {
 pub mod capnp {
     use capnp::{
         message::{TypedBuilder, TypedReader},
-        serialize::SliceSegments,
+        serialize::OwnedSegments,
     };
 
     use super::format::capnp::Capnp;
     pub type Publisher<T> = super::Publisher<TypedBuilder<T>, Capnp<T>>;
-    pub type Subscriber<'a, T> = super::Subscriber<TypedReader<SliceSegments<'a>, T>, Capnp<T>>;
+    pub type Subscriber<T> = super::Subscriber<TypedReader<OwnedSegments, T>, Capnp<T>>;
 }
 
-pub struct Publisher<T, S> {
-    handle: sys::ECAL_HANDLE,
-    _ty: PhantomData<T>,
-    _serializer: PhantomData<S>,
+/// History policy of a [`Qos`] setting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum History {
+    /// Keep only the last `depth` samples.
+    KeepLast(u32),
+    /// Keep all samples until the resource limits are exhausted.
+    KeepAll,
 }
 
-impl<T, S> Publisher<T, S>
-where
-    S: format::Format + format::Serializer<T>,
-{
-    pub fn new(topic_name: &str) -> Result<Self> {
-        let handle = unsafe { sys::eCAL_Pub_New() };
-        let c_topic_name = ffi::CString::new(topic_name)?;
-        let c_topic_type = ffi::CString::new(S::topic_type())?;
-        let description = S::topic_description();
-        let c_description = match description {
-            Some(description) => ffi::CString::new(description)?,
-            None => ffi::CString::default(),
+/// Reliability policy of a [`Qos`] setting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Reliability {
+    BestEffort,
+    Reliable,
+}
+
+/// Quality of service policies of a [`Publisher`] or [`Subscriber`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Qos {
+    pub history: History,
+    pub reliability: Reliability,
+}
+
+impl Qos {
+    fn to_sys(
+        self,
+    ) -> (
+        sys::eQOSPolicy_HistoryKindC,
+        c_int,
+        sys::eQOSPolicy_ReliabilityC,
+    ) {
+        use sys::eQOSPolicy_HistoryKindC::*;
+        use sys::eQOSPolicy_ReliabilityC::*;
+        let (history_kind, depth) = match self.history {
+            History::KeepLast(depth) => (
+                keep_last_history_qos,
+                c_int::try_from(depth).unwrap_or(c_int::MAX),
+            ),
+            History::KeepAll => (keep_all_history_qos, 0),
         };
-        let status = unsafe {
-            sys::eCAL_Pub_Create(
-                handle,
-                c_topic_name.as_ptr(),
-                c_topic_type.as_ptr(),
-                c_description.as_ptr() as *const std::os::raw::c_char,
-                c_description.as_bytes().len() as i32,
-            )
+        let reliability = match self.reliability {
+            Reliability::BestEffort => best_effort_reliability_qos,
+            Reliability::Reliable => reliable_reliability_qos,
         };
-        if status == 0 {
-            unsafe {
-                sys::eCAL_Pub_Destroy(handle);
-            }
-            Err(CalError::PublisherCreationFailed(topic_name.to_string()).into())
-        } else {
-            Ok(Publisher {
-                handle,
-                _serializer: Default::default(),
-                _ty: Default::default(),
-            })
+        (history_kind, depth, reliability)
+    }
+
+    fn from_sys(
+        history_kind: sys::eQOSPolicy_HistoryKindC,
+        depth: c_int,
+        reliability: sys::eQOSPolicy_ReliabilityC,
+    ) -> Self {
+        use sys::eQOSPolicy_HistoryKindC::*;
+        use sys::eQOSPolicy_ReliabilityC::*;
+        let history = match history_kind {
+            keep_last_history_qos => History::KeepLast(depth.max(0) as u32),
+            keep_all_history_qos => History::KeepAll,
+        };
+        let reliability = match reliability {
+            best_effort_reliability_qos => Reliability::BestEffort,
+            reliable_reliability_qos => Reliability::Reliable,
+        };
+        Qos {
+            history,
+            reliability,
         }
     }
+}
 
-    pub fn set_id(&mut self, id: i64) -> bool {
-        unsafe { sys::eCAL_Pub_SetID(self.handle, id as c_longlong) != 0 }
+impl From<Qos> for sys::SWriterQOSC {
+    fn from(qos: Qos) -> Self {
+        let (history_kind, history_kind_depth, reliability) = qos.to_sys();
+        sys::SWriterQOSC {
+            history_kind,
+            history_kind_depth,
+            reliability,
+        }
     }
+}
 
-    pub fn shm_set_buffer_count(&mut self, buffer_num: usize) -> bool {
-        unsafe { sys::eCAL_Pub_ShmSetBufferCount(self.handle, buffer_num as c_long) != 0 }
+impl From<sys::SWriterQOSC> for Qos {
+    fn from(qos: sys::SWriterQOSC) -> Self {
+        Qos::from_sys(qos.history_kind, qos.history_kind_depth, qos.reliability)
     }
+}
 
-    pub fn is_subscribed(&self) -> bool {
-        unsafe { sys::eCAL_Pub_IsSubscribed(self.handle) != 0 }
+impl From<Qos> for sys::SReaderQOSC {
+    fn from(qos: Qos) -> Self {
+        let (history_kind, history_kind_depth, reliability) = qos.to_sys();
+        sys::SReaderQOSC {
+            history_kind,
+            history_kind_depth,
+            reliability,
+        }
     }
+}
 
-    pub fn send(&self, msg: &T) -> Result<()> {
-        self.send_with_time(msg, -1)
+impl From<sys::SReaderQOSC> for Qos {
+    fn from(qos: sys::SReaderQOSC) -> Self {
+        Qos::from_sys(qos.history_kind, qos.history_kind_depth, qos.reliability)
     }
+}
 
-    /// Same as [send](#method.send) but let the caller set the time of the message
-    pub fn send_with_time(&self, msg: &T, time: i64) -> Result<()> {
-        let mut buf = Vec::with_capacity(32);
-        S::serialize(msg, &mut buf)?;
+/// Transport layers a [`Publisher`] can send on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransportLayer {
+    UdpMulticast,
+    Shm,
+    Inproc,
+    All,
+}
 
-        let bytes_expected = buf.len();
-        let bytes_sent = unsafe {
-            sys::eCAL_Pub_Send(
-                self.handle,
-                buf.as_ptr() as *const c_void,
-                bytes_expected as c_int,
-                time as c_longlong,
-            )
-        };
-        log::trace!("Published {} / {} bytes", bytes_sent, bytes_expected);
-        if bytes_sent != bytes_expected as c_int {
-            Err(CalError::PublishFailed.into())
-        } else {
-            Ok(())
+impl From<TransportLayer> for sys::eTransportLayerC {
+    fn from(layer: TransportLayer) -> Self {
+        use sys::eTransportLayerC::*;
+        use TransportLayer::*;
+        match layer {
+            UdpMulticast => tlayer_udp_mc,
+            Shm => tlayer_shm,
+            Inproc => tlayer_inproc,
+            All => tlayer_all,
         }
     }
+}
 
-    unsafe extern "C" fn event_wrapper<F>(
-        _topic_name: *const c_char,
-        _data: *const sys::SPubEventCallbackDataC,
-        ctx: *mut c_void,
-    ) where
-        F: FnMut(),
-    {
-        // TODO: Support other callback types. :D
-        let cb_ptr = ctx as *mut F;
-        let callback = &mut *cb_ptr;
-        callback();
-    }
+/// Send mode of a single [`TransportLayer`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SendMode {
+    Off,
+    On,
+    /// Let eCAL enable the layer depending on where the subscribers are.
+    Auto,
+}
 
-    pub fn on_subscribed<F>(&self, callback: F)
-    where
-        F: FnMut(),
-    {
-        // TODO: memory leak?
-        let callback = Box::into_raw(Box::new(callback));
-        unsafe {
-            sys::eCAL_Pub_AddEventCallbackC(
-                self.handle,
-                sys::eCAL_Publisher_Event::pub_event_connected,
-                Some(Self::event_wrapper::<F>),
-                callback as *mut _,
-            );
+impl From<SendMode> for sys::eSendModeC {
+    fn from(mode: SendMode) -> Self {
+        use sys::eSendModeC::*;
+        use SendMode::*;
+        match mode {
+            Off => smode_off,
+            On => smode_on,
+            Auto => smode_auto,
         }
     }
 }
 
-impl<T, S> Drop for Publisher<T, S> {
-    fn drop(&mut self) {
-        unsafe {
-            sys::eCAL_Pub_Destroy(self.handle);
+/// Maximum transmit bandwidth of the UDP layer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bandwidth {
+    Unlimited,
+    BytesPerSecond(u64),
+}
+
+impl From<Bandwidth> for c_long {
+    fn from(bandwidth: Bandwidth) -> Self {
+        match bandwidth {
+            Bandwidth::Unlimited => -1,
+            Bandwidth::BytesPerSecond(bytes) => c_long::try_from(bytes).unwrap_or(c_long::MAX),
         }
     }
 }
 
-pub type RecvFn<T> = dyn Fn(Instant, T);
-
-pub struct Subscriber<T, D> {
-    handle: sys::ECAL_HANDLE,
-    _ty: PhantomData<T>,
-    _deserializer: PhantomData<D>,
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PublisherEventKind {
+    Connected,
+    Disconnected,
+    Dropped,
 }
 
-impl<'a, T, D> Subscriber<T, D>
-where
-    D: format::Format + format::Deserializer<'a, T>,
-{
-    pub fn new(topic_name: &str) -> Result<Self> {
-        let handle = unsafe { sys::eCAL_Sub_New() };
-        let c_topic_name = ffi::CString::new(topic_name)?;
-        let c_topic_type = ffi::CString::new(D::topic_type())?;
-        let description = D::topic_description();
-        let c_description = match description {
-            Some(description) => ffi::CString::new(description)?,
-            None => ffi::CString::default(),
-        };
-        let status = unsafe {
-            sys::eCAL_Sub_Create(
-                handle,
-                c_topic_name.as_ptr(),
-                c_topic_type.as_ptr(),
-                c_description.as_ptr() as *const std::os::raw::c_char,
-                c_description.as_bytes().len() as i32,
-            )
-        };
-        if status == 0 {
-            Err(CalError::SubscriberCreationFailed(topic_name.to_string()).into())
-        } else {
-            Ok(Subscriber {
-                handle,
-                _ty: Default::default(),
-                _deserializer: Default::default(),
-            })
+impl From<PublisherEventKind> for sys::eCAL_Publisher_Event {
+    fn from(kind: PublisherEventKind) -> Self {
+        use sys::eCAL_Publisher_Event::*;
+        use PublisherEventKind::*;
+        match kind {
+            Connected => pub_event_connected,
+            Disconnected => pub_event_disconnected,
+            Dropped => pub_event_dropped,
         }
     }
+}
 
-    fn _recv(&self, timeout: c_int) -> Result<T> {
-        let mut buf = ptr::null_mut::<c_void>();
-        let buf_len = sys::ECAL_ALLOCATE_4ME as i32;
-        let mut time = 0;
-
-        let bytes_received =
-            unsafe { sys::eCAL_Sub_Receive(self.handle, &mut buf, buf_len, &mut time, timeout) };
+/// Event reported by eCAL for a [`Publisher`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PublisherEvent {
+    pub kind: PublisherEventKind,
+    /// eCAL time of the event in microseconds.
+    pub time: i64,
+    pub clock: i64,
+}
 
-        if bytes_received > 0 {
-            let bytes = unsafe { slice::from_raw_parts(buf as *const u8, bytes_received as usize) };
+impl PublisherEvent {
+    const KINDS: [PublisherEventKind; 3] = [
+        PublisherEventKind::Connected,
+        PublisherEventKind::Disconnected,
+        PublisherEventKind::Dropped,
+    ];
+
+    fn from_sys(data: &sys::SPubEventCallbackDataC) -> Option<Self> {
+        use sys::eCAL_Publisher_Event::*;
+        let kind = match data.type_ {
+            pub_event_connected => PublisherEventKind::Connected,
+            pub_event_disconnected => PublisherEventKind::Disconnected,
+            pub_event_dropped => PublisherEventKind::Dropped,
+            pub_event_none => return None,
+        };
+        Some(PublisherEvent {
+            kind,
+            time: data.time,
+            clock: data.clock,
+        })
+    }
+}
 
-            let res = D::deserialize(bytes).map_err(|err| {
-                log::error!("Failed to decode message: {}", err);
-                CalError::InvalidFormat.into()
-            });
+/// eCAL publisher handle, shared with the guards of its callbacks.
+struct PublisherHandle {
+    raw: sys::ECAL_HANDLE,
+    events: Registry<PublisherEvent>,
+}
 
-            log::trace!("Freeing recv buffer");
-            unsafe {
-                sys::eCAL_FreeMem(buf);
+impl PublisherHandle {
+    unsafe extern "C" fn event_trampoline(
+        _topic_name: *const c_char,
+        data: *const sys::SPubEventCallbackDataC,
+        ctx: *mut c_void,
+    ) {
+        let events = &*(ctx as *const Registry<PublisherEvent>);
+        callback::catch_unwind("Publisher event callback", || {
+            if let Some(event) = PublisherEvent::from_sys(&*data) {
+                events.dispatch(&event);
             }
+        });
+    }
 
-            res
-        } else {
-            log::trace!("Subscriber timeout");
-            if !buf.is_null() {
-                log::warn!("Non-null pointer returned from recv, but bytes_received was 0!");
+    fn add_event_callbacks(&self) -> bool {
+        let ctx = &self.events as *const Registry<PublisherEvent> as *mut c_void;
+        for (added, kind) in PublisherEvent::KINDS.iter().enumerate() {
+            let status = unsafe {
+                sys::eCAL_Pub_AddEventCallbackC(
+                    self.raw,
+                    (*kind).into(),
+                    Some(Self::event_trampoline),
+                    ctx,
+                )
+            };
+            if status == 0 {
+                for kind in &PublisherEvent::KINDS[..added] {
+                    unsafe { sys::eCAL_Pub_RemEventCallback(self.raw, (*kind).into()) };
+                }
+                return false;
             }
-            Err(CalError::Timeout.into())
         }
+        true
     }
 
-    pub fn recv(&self) -> Result<T> {
-        log::trace!("Subscriber::recv");
-        self._recv(-1).map_err(Into::into)
+    fn rem_event_callbacks(&self) {
+        for kind in PublisherEvent::KINDS.iter() {
+            unsafe { sys::eCAL_Pub_RemEventCallback(self.raw, (*kind).into()) };
+        }
     }
+}
 
-    pub fn try_recv(&self, timeout: Duration) -> Option<T> {
-        log::trace!("Subscriber::try_recv");
-        let timeout = timeout.as_millis() as c_int;
-        self._recv(timeout).ok()
+impl Drop for PublisherHandle {
+    fn drop(&mut self) {
+        // Destroying the publisher stops all callbacks, after which the remaining
+        // closures are freed together with the registry.
+        unsafe {
+            sys::eCAL_Pub_Destroy(self.raw);
+        }
     }
+}
 
-    unsafe extern "C" fn recv_wrapper<F>(
-        _topic_name: *const c_char,
-        data: *const sys::SReceiveCallbackDataC,
-        ctx: *mut c_void,
-    ) where
-        F: FnMut(Instant, T),
-    {
-        let bytes = slice::from_raw_parts((*data).buf as *const u8, (*data).size as usize);
+unsafe impl Send for PublisherHandle {}
+unsafe impl Sync for PublisherHandle {}
 
-        if let Ok(msg) = D::deserialize(bytes) {
-            log::trace!("Received {} bytes", bytes.len());
-            let cb_ptr = ctx as *mut F;
-            let callback = &mut *cb_ptr;
-            // TODO: use eCAL timestamp
-            let timestamp = Instant::now();
-            callback(timestamp, msg);
+/// Publisher sending untyped payloads, e.g. for relays and gateways.
+///
+/// The topic type and description are passed to eCAL as given.
+pub struct RawPublisher {
+    handle: Arc<PublisherHandle>,
+    subscribers: Arc<Connections>,
+}
+
+impl RawPublisher {
+    pub fn new(topic_name: &str, topic_type: &str, topic_description: &[u8]) -> Result<Self> {
+        Self::builder(topic_name)
+            .topic_type(topic_type)
+            .topic_description(topic_description)
+            .build()
+    }
+
+    pub fn builder(topic_name: &str) -> PublisherBuilder<Self> {
+        PublisherBuilder::new(topic_name)
+    }
+
+    pub fn set_id(&mut self, id: i64) -> bool {
+        unsafe { sys::eCAL_Pub_SetID(self.handle.raw, id as c_longlong) != 0 }
+    }
+
+    pub fn shm_set_buffer_count(&mut self, buffer_num: usize) -> bool {
+        unsafe { sys::eCAL_Pub_ShmSetBufferCount(self.handle.raw, buffer_num as c_long) != 0 }
+    }
+
+    /// Sets the quality of service policies of this publisher.
+    ///
+    /// eCAL may reject changes once the topic is created, see [`PublisherBuilder::qos`].
+    pub fn set_qos(&mut self, qos: Qos) -> Result<()> {
+        let status = unsafe { sys::eCAL_Pub_SetQOS(self.handle.raw, qos.into()) };
+        if status == 0 {
+            Err(CalError::SettingRejected("qos"))
         } else {
-            log::error!("Failed to decode message.");
+            Ok(())
         }
     }
 
-    pub fn on_recv<'b, F: FnMut(Instant, T) + 'b>(&'b self, callback: F) {
-        // TODO: memory leak?
-        let callback = Box::into_raw(Box::new(callback));
-        unsafe {
-            sys::eCAL_Sub_AddReceiveCallbackC(
-                self.handle,
-                Some(Self::recv_wrapper::<F>),
-                callback as *mut _,
-            );
+    /// Returns the quality of service policies currently used by this publisher.
+    pub fn qos(&self) -> Result<Qos> {
+        let mut qos = sys::SWriterQOSC::default();
+        let status = unsafe { sys::eCAL_Pub_GetQOS(self.handle.raw, &mut qos) };
+        if status == 0 {
+            Err(CalError::SettingRejected("qos"))
+        } else {
+            Ok(qos.into())
         }
     }
 
-    unsafe extern "C" fn recv_wrapper_full<F>(
-        _topic_name: *const c_char,
-        data: *const sys::SReceiveCallbackDataC,
-        ctx: *mut c_void,
-    ) where
-        F: FnMut(sys::SReceiveCallbackDataC, T),
-    {
-        let bytes = slice::from_raw_parts((*data).buf as *const u8, (*data).size as usize);
-
-        if let Ok(msg) = D::deserialize(bytes) {
-            log::trace!("Received {} bytes", bytes.len());
-            let cb_ptr = ctx as *mut F;
-            let callback = &mut *cb_ptr;
-            callback(*data, msg);
+    /// Sets the send mode of a single transport layer, e.g. to force a topic onto SHM only.
+    pub fn set_layer_mode(&mut self, layer: TransportLayer, mode: SendMode) -> Result<()> {
+        let status =
+            unsafe { sys::eCAL_Pub_SetLayerMode(self.handle.raw, layer.into(), mode.into()) };
+        if status == 0 {
+            Err(CalError::SettingRejected("layer_mode"))
         } else {
-            log::error!("Failed to decode message.");
+            Ok(())
         }
     }
 
-    /// Same as [`on_recv`](#method.on_recv), but instead of pass the Instant of the message this will pass
-    /// the entire content that arrives from the receive callback ([SReceiveCallbackDataC](sys::SReceiveCallbackDataC))
-    pub fn on_recv_full<'b, F: FnMut(sys::SReceiveCallbackDataC, T) + 'b>(&'b self, callback: F) {
-        // TODO: memory leak?
-        let callback = Box::into_raw(Box::new(callback));
-        unsafe {
-            sys::eCAL_Sub_AddReceiveCallbackC(
-                self.handle,
-                Some(Self::recv_wrapper_full::<F>),
-                callback as *mut _,
-            );
+    /// Limits the transmit bandwidth of the UDP layer.
+    pub fn set_max_bandwidth_udp(&mut self, bandwidth: Bandwidth) -> Result<()> {
+        let status = unsafe { sys::eCAL_Pub_SetMaxBandwidthUDP(self.handle.raw, bandwidth.into()) };
+        if status == 0 {
+            Err(CalError::SettingRejected("max_bandwidth_udp"))
+        } else {
+            Ok(())
         }
     }
-}
 
-impl<T, D> Drop for Subscriber<T, D> {
-    fn drop(&mut self) {
-        unsafe {
-            sys::eCAL_Sub_Destroy(self.handle);
-        }
+    pub fn is_subscribed(&self) -> bool {
+        unsafe { sys::eCAL_Pub_IsSubscribed(self.handle.raw) != 0 }
     }
-}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum NodeState {
-    Healthy,
-    Critical,
-    Failed,
-    Unknown,
-    Warning,
-}
+    /// Blocks until a subscriber is connected, returning `false` if none connected
+    /// within `timeout`.
+    pub fn wait_for_subscribers(&self, timeout: Duration) -> bool {
+        self.subscribers.wait(timeout)
+    }
 
-impl From<NodeState> for sys::eCAL_Process_eSeverity {
-    fn from(state: NodeState) -> Self {
-        use sys::eCAL_Process_eSeverity::*;
-        use NodeState::*;
-        match state {
-            Healthy => proc_sev_healthy,
-            Critical => proc_sev_critical,
-            Failed => proc_sev_failed,
-            Unknown => proc_sev_unknown,
-            Warning => proc_sev_warning,
-        }
+    /// Async version of [`wait_for_subscribers`](#method.wait_for_subscribers).
+    #[cfg(feature = "async")]
+    pub fn wait_for_subscribers_async(&self, timeout: Duration) -> WaitForConnection {
+        WaitForConnection::new(self.subscribers.clone(), timeout)
     }
-}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum SeverityLevel {
-    Level1,
-    Level2,
-    Level3,
-    Level4,
-    Level5,
-}
+    pub fn send(&self, payload: &[u8]) -> Result<()> {
+        self.send_with_time(payload, -1)
+    }
 
-impl From<SeverityLevel> for sys::eCAL_Process_eSeverity_Level {
-    fn from(level: SeverityLevel) -> Self {
-        use sys::eCAL_Process_eSeverity_Level::*;
-        use SeverityLevel::*;
-        match level {
-            Level1 => proc_sev_level1,
-            Level2 => proc_sev_level2,
-            Level3 => proc_sev_level3,
-            Level4 => proc_sev_level4,
-            Level5 => proc_sev_level5,
+    /// Same as [send](#method.send) but let the caller set the time of the message
+    pub fn send_with_time(&self, payload: &[u8], time: i64) -> Result<()> {
+        let bytes_expected = payload.len();
+        let bytes_sent = unsafe {
+            sys::eCAL_Pub_Send(
+                self.handle.raw,
+                payload.as_ptr() as *const c_void,
+                bytes_expected as c_int,
+                time as c_longlong,
+            )
+        };
+        log::trace!("Published {} / {} bytes", bytes_sent, bytes_expected);
+        if bytes_sent != bytes_expected as c_int {
+            Err(CalError::PublishFailed)
+        } else {
+            Ok(())
         }
     }
-}
 
-#[derive(Debug, Default)]
-pub struct Cal {
-    status_msg: ffi::CString,
-}
+    /// Registers `callback` for the connect, disconnect and drop events of this publisher.
+    ///
+    /// The callback runs on an eCAL thread until the returned guard is dropped and
+    /// must not drop callback guards of this publisher itself.
+    pub fn on_event<F>(&self, mut callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(PublisherEvent) + Send + 'static,
+    {
+        callback::listen(
+            &self.handle,
+            |handle| &handle.events,
+            PublisherHandle::add_event_callbacks,
+            PublisherHandle::rem_event_callbacks,
+            Box::new(move |event| callback(*event)),
+        )
+        .ok_or(CalError::CallbackRegistrationFailed)
+    }
 
-impl Cal {
-    pub fn new(unit_name: &str) -> Result<Self> {
-        initialize(unit_name).and_then(|_| {
-            let mut cal = Cal::default();
-            cal.set_state(NodeState::Healthy, SeverityLevel::Level1, "ok")?;
-            Ok(cal)
+    /// Same as [`on_event`](#method.on_event), but only for subscribers connecting.
+    pub fn on_subscribed<F>(&self, mut callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.on_event(move |event| {
+            if event.kind == PublisherEventKind::Connected {
+                callback();
+            }
         })
     }
+}
 
-    /// Sets the process state and severity level.
-    /// Can fail if the status message is unable to be
-    /// converted to a CString.
-    pub fn set_state(&mut self, state: NodeState, level: SeverityLevel, info: &str) -> Result<()> {
-        self.status_msg = ffi::CString::new(info)?;
-        unsafe { sys::eCAL_Process_SetState(state.into(), level.into(), self.status_msg.as_ptr()) };
-        Ok(())
-    }
+pub struct Publisher<T, S> {
+    raw: RawPublisher,
+    /// Reused for every message, so steady-state sending does not allocate.
+    buffer: Mutex<Vec<u8>>,
+    _ty: PhantomData<T>,
+    _serializer: PhantomData<S>,
 }
 
-impl Drop for Cal {
-    fn drop(&mut self) {
-        finalize();
+impl<T, S> Publisher<T, S>
+where
+    S: format::Format + format::Serializer<T>,
+{
+    pub fn new(topic_name: &str) -> Result<Self> {
+        Self::builder(topic_name).build()
     }
-}
 
-fn initialize(unit_name: &str) -> Result<()> {
+    pub fn builder(topic_name: &str) -> PublisherBuilder<Self> {
+        PublisherBuilder::new(topic_name)
+    }
+
+    /// The untyped publisher this publisher sends through.
+    pub fn raw(&self) -> &RawPublisher {
+        &self.raw
+    }
+
+    pub fn set_id(&mut self, id: i64) -> bool {
+        self.raw.set_id(id)
+    }
+
+    pub fn shm_set_buffer_count(&mut self, buffer_num: usize) -> bool {
+        self.raw.shm_set_buffer_count(buffer_num)
+    }
+
+    /// Sets the quality of service policies of this publisher.
+    pub fn set_qos(&mut self, qos: Qos) -> Result<()> {
+        self.raw.set_qos(qos)
+    }
+
+    /// Returns the quality of service policies currently used by this publisher.
+    pub fn qos(&self) -> Result<Qos> {
+        self.raw.qos()
+    }
+
+    /// Sets the send mode of a single transport layer, e.g. to force a topic onto SHM only.
+    pub fn set_layer_mode(&mut self, layer: TransportLayer, mode: SendMode) -> Result<()> {
+        self.raw.set_layer_mode(layer, mode)
+    }
+
+    /// Limits the transmit bandwidth of the UDP layer.
+    pub fn set_max_bandwidth_udp(&mut self, bandwidth: Bandwidth) -> Result<()> {
+        self.raw.set_max_bandwidth_udp(bandwidth)
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.raw.is_subscribed()
+    }
+
+    /// Blocks until a subscriber is connected, returning `false` if none connected
+    /// within `timeout`.
+    pub fn wait_for_subscribers(&self, timeout: Duration) -> bool {
+        self.raw.wait_for_subscribers(timeout)
+    }
+
+    /// Async version of [`wait_for_subscribers`](#method.wait_for_subscribers).
+    #[cfg(feature = "async")]
+    pub fn wait_for_subscribers_async(&self, timeout: Duration) -> WaitForConnection {
+        self.raw.wait_for_subscribers_async(timeout)
+    }
+
+    pub fn send(&self, msg: &T) -> Result<()> {
+        self.send_with_time(msg, -1)
+    }
+
+    /// Same as [send](#method.send) but let the caller set the time of the message
+    pub fn send_with_time(&self, msg: &T, time: i64) -> Result<()> {
+        Self::encode(&self.buffer, msg, |payload| {
+            self.raw.send_with_time(payload, time)
+        })
+    }
+
+    /// Encodes `msg` into `buffer` and hands the payload to `send`.
+    fn encode(
+        buffer: &Mutex<Vec<u8>>,
+        msg: &T,
+        send: impl FnOnce(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        // A concurrent send already owns the shared buffer, fall back to a fresh one
+        // rather than waiting for it.
+        let mut shared = buffer.try_lock().ok();
+        let mut fresh = Vec::new();
+        let buf = shared.as_deref_mut().unwrap_or(&mut fresh);
+
+        buf.clear();
+        buf.reserve(S::encoded_len(msg).unwrap_or(32));
+        S::serialize(msg, buf).map_err(CalError::Encode)?;
+        send(buf)
+    }
+
+    /// Registers `callback` for the connect, disconnect and drop events of this publisher.
+    ///
+    /// The callback runs on an eCAL thread until the returned guard is dropped and
+    /// must not drop callback guards of this publisher itself.
+    pub fn on_event<F>(&self, callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(PublisherEvent) + Send + 'static,
+    {
+        self.raw.on_event(callback)
+    }
+
+    /// Same as [`on_event`](#method.on_event), but only for subscribers connecting.
+    pub fn on_subscribed<F>(&self, callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.raw.on_subscribed(callback)
+    }
+}
+
+/// Declarative setup of a [`Publisher`] or [`RawPublisher`].
+///
+/// All settings are applied while building, quality of service before the topic is
+/// created and everything else right after it. The first rejected setting is
+/// reported as [`CalError::SettingRejected`].
+pub struct PublisherBuilder<P> {
+    topic_name: String,
+    topic_type: Option<String>,
+    topic_description: Option<Vec<u8>>,
+    share_type: Option<bool>,
+    share_description: Option<bool>,
+    id: Option<i64>,
+    shm_buffer_count: Option<usize>,
+    qos: Option<Qos>,
+    layer_modes: Vec<(TransportLayer, SendMode)>,
+    max_bandwidth_udp: Option<Bandwidth>,
+    _publisher: PhantomData<P>,
+}
+
+impl<P> PublisherBuilder<P> {
+    pub fn new(topic_name: &str) -> Self {
+        PublisherBuilder {
+            topic_name: topic_name.to_string(),
+            topic_type: None,
+            topic_description: None,
+            share_type: None,
+            share_description: None,
+            id: None,
+            shm_buffer_count: None,
+            qos: None,
+            layer_modes: Vec::new(),
+            max_bandwidth_udp: None,
+            _publisher: Default::default(),
+        }
+    }
+
+    /// Overrides the topic type registered with eCAL.
+    pub fn topic_type(mut self, topic_type: &str) -> Self {
+        self.topic_type = Some(topic_type.to_string());
+        self
+    }
+
+    /// Overrides the topic description registered with eCAL.
+    pub fn topic_description(mut self, topic_description: &[u8]) -> Self {
+        self.topic_description = Some(topic_description.to_vec());
+        self
+    }
+
+    /// Whether the topic type is shared with the rest of the system.
+    pub fn share_type(mut self, share: bool) -> Self {
+        self.share_type = Some(share);
+        self
+    }
+
+    /// Whether the topic description is shared with the rest of the system.
+    pub fn share_description(mut self, share: bool) -> Self {
+        self.share_description = Some(share);
+        self
+    }
+
+    /// See [`Publisher::set_id`].
+    pub fn id(mut self, id: i64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// See [`Publisher::shm_set_buffer_count`].
+    pub fn shm_buffer_count(mut self, buffer_num: usize) -> Self {
+        self.shm_buffer_count = Some(buffer_num);
+        self
+    }
+
+    pub fn qos(mut self, qos: Qos) -> Self {
+        self.qos = Some(qos);
+        self
+    }
+
+    /// See [`Publisher::set_layer_mode`]. May be given once per layer.
+    pub fn layer_mode(mut self, layer: TransportLayer, mode: SendMode) -> Self {
+        self.layer_modes.push((layer, mode));
+        self
+    }
+
+    pub fn max_bandwidth_udp(mut self, bandwidth: Bandwidth) -> Self {
+        self.max_bandwidth_udp = Some(bandwidth);
+        self
+    }
+
+    fn create(self, topic_type: String, topic_description: Vec<u8>) -> Result<RawPublisher> {
+        let topic_type = self.topic_type.unwrap_or(topic_type);
+        let topic_description = self.topic_description.unwrap_or(topic_description);
+
+        let handle = Arc::new(PublisherHandle {
+            raw: unsafe { sys::eCAL_Pub_New() },
+            events: Registry::new(),
+        });
+        let weak = Arc::downgrade(&handle);
+        let subscribers = Connections::new(move || {
+            weak.upgrade()
+                .is_some_and(|handle| unsafe { sys::eCAL_Pub_IsSubscribed(handle.raw) != 0 })
+        });
+        let mut publisher = RawPublisher {
+            handle,
+            subscribers: Arc::new(subscribers),
+        };
+        if let Some(qos) = self.qos {
+            publisher.set_qos(qos)?;
+        }
+
+        let c_topic_name = ffi::CString::new(self.topic_name.as_str())?;
+        let c_topic_type = ffi::CString::new(topic_type)?;
+        let status = unsafe {
+            sys::eCAL_Pub_Create(
+                publisher.handle.raw,
+                c_topic_name.as_ptr(),
+                c_topic_type.as_ptr(),
+                topic_description.as_ptr() as *const c_char,
+                topic_description.len() as c_int,
+            )
+        };
+        if status == 0 {
+            return Err(CalError::PublisherCreationFailed(self.topic_name));
+        }
+
+        let subscribers = publisher.subscribers.clone();
+        publisher
+            .on_event(move |event| match event.kind {
+                PublisherEventKind::Connected => subscribers.connected(),
+                PublisherEventKind::Disconnected => subscribers.disconnected(),
+                PublisherEventKind::Dropped => {}
+            })?
+            .forget();
+
+        let raw = publisher.handle.raw;
+        if let Some(share) = self.share_type {
+            if unsafe { sys::eCAL_Pub_ShareType(raw, share as c_int) } == 0 {
+                return Err(CalError::SettingRejected("share_type"));
+            }
+        }
+        if let Some(share) = self.share_description {
+            if unsafe { sys::eCAL_Pub_ShareDescription(raw, share as c_int) } == 0 {
+                return Err(CalError::SettingRejected("share_description"));
+            }
+        }
+        if let Some(id) = self.id {
+            if !publisher.set_id(id) {
+                return Err(CalError::SettingRejected("id"));
+            }
+        }
+        if let Some(buffer_num) = self.shm_buffer_count {
+            if !publisher.shm_set_buffer_count(buffer_num) {
+                return Err(CalError::SettingRejected("shm_buffer_count"));
+            }
+        }
+        for (layer, mode) in self.layer_modes {
+            publisher.set_layer_mode(layer, mode)?;
+        }
+        if let Some(bandwidth) = self.max_bandwidth_udp {
+            publisher.set_max_bandwidth_udp(bandwidth)?;
+        }
+
+        Ok(publisher)
+    }
+}
+
+impl PublisherBuilder<RawPublisher> {
+    /// Creates the publisher. Without an explicit topic type, an empty one is registered.
+    pub fn build(self) -> Result<RawPublisher> {
+        self.create(String::new(), Vec::new())
+    }
+}
+
+impl<T, S> PublisherBuilder<Publisher<T, S>>
+where
+    S: format::Format + format::Serializer<T>,
+{
+    /// Creates the publisher, registering the type and description of `S` unless overridden.
+    pub fn build(self) -> Result<Publisher<T, S>> {
+        let description = S::topic_description().unwrap_or_default();
+        let raw = self.create(S::topic_type(), description.into_bytes())?;
+        Ok(Publisher {
+            raw,
+            buffer: Mutex::new(Vec::new()),
+            _serializer: Default::default(),
+            _ty: Default::default(),
+        })
+    }
+}
+
+/// Metadata eCAL reports together with a received message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SampleInfo {
+    /// Send time of the publisher in microseconds (eCAL time).
+    pub time: i64,
+    /// Write clock of the publisher. Only reported to receive callbacks, always
+    /// `None` for messages taken with `recv` or `try_recv`.
+    pub clock: Option<i64>,
+    /// Id set with [`Publisher::set_id`], see also [`Subscriber::set_id_filter`].
+    /// Only reported to receive callbacks, always `None` for messages taken with
+    /// `recv` or `try_recv`.
+    pub id: Option<i64>,
+    /// Size of the serialized payload in bytes.
+    pub size: usize,
+}
+
+impl SampleInfo {
+    fn from_sys(data: &sys::SReceiveCallbackDataC) -> Self {
+        SampleInfo {
+            time: data.time,
+            clock: Some(data.clock),
+            id: Some(data.id),
+            size: data.size as usize,
+        }
+    }
+}
+
+/// A received message together with its [`SampleInfo`].
+///
+/// Dereferences to the message itself.
+#[derive(Debug, Clone)]
+pub struct Received<T> {
+    pub msg: T,
+    pub info: SampleInfo,
+}
+
+impl<T> Received<T> {
+    pub fn into_inner(self) -> T {
+        self.msg
+    }
+}
+
+impl<T> Deref for Received<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.msg
+    }
+}
+
+impl<T> DerefMut for Received<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.msg
+    }
+}
+
+pub type RecvFn<T> = dyn Fn(Received<T>);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SubscriberEventKind {
+    Connected,
+    Disconnected,
+    Dropped,
+    /// No message arrived within the timeout set by [`Subscriber::set_timeout`].
+    Timeout,
+    Corrupted,
+}
+
+impl From<SubscriberEventKind> for sys::eCAL_Subscriber_Event {
+    fn from(kind: SubscriberEventKind) -> Self {
+        use sys::eCAL_Subscriber_Event::*;
+        use SubscriberEventKind::*;
+        match kind {
+            Connected => sub_event_connected,
+            Disconnected => sub_event_disconnected,
+            Dropped => sub_event_dropped,
+            Timeout => sub_event_timeout,
+            Corrupted => sub_event_corrupted,
+        }
+    }
+}
+
+/// Event reported by eCAL for a [`Subscriber`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubscriberEvent {
+    pub kind: SubscriberEventKind,
+    /// eCAL time of the event in microseconds.
+    pub time: i64,
+    pub clock: i64,
+}
+
+impl SubscriberEvent {
+    const KINDS: [SubscriberEventKind; 5] = [
+        SubscriberEventKind::Connected,
+        SubscriberEventKind::Disconnected,
+        SubscriberEventKind::Dropped,
+        SubscriberEventKind::Timeout,
+        SubscriberEventKind::Corrupted,
+    ];
+
+    fn from_sys(data: &sys::SSubEventCallbackDataC) -> Option<Self> {
+        use sys::eCAL_Subscriber_Event::*;
+        let kind = match data.type_ {
+            sub_event_connected => SubscriberEventKind::Connected,
+            sub_event_disconnected => SubscriberEventKind::Disconnected,
+            sub_event_dropped => SubscriberEventKind::Dropped,
+            sub_event_timeout => SubscriberEventKind::Timeout,
+            sub_event_corrupted => SubscriberEventKind::Corrupted,
+            sub_event_none => return None,
+        };
+        Some(SubscriberEvent {
+            kind,
+            time: data.time,
+            clock: data.clock,
+        })
+    }
+}
+
+/// eCAL subscriber handle, shared with the guards of its callbacks.
+struct SubscriberHandle {
+    raw: sys::ECAL_HANDLE,
+    events: Registry<SubscriberEvent>,
+    received: Registry<sys::SReceiveCallbackDataC>,
+}
+
+impl SubscriberHandle {
+    unsafe extern "C" fn receive_trampoline(
+        _topic_name: *const c_char,
+        data: *const sys::SReceiveCallbackDataC,
+        ctx: *mut c_void,
+    ) {
+        let received = &*(ctx as *const Registry<sys::SReceiveCallbackDataC>);
+        callback::catch_unwind("Receive callback", || received.dispatch(&*data));
+    }
+
+    fn add_receive_callback(&self) -> bool {
+        let ctx = &self.received as *const Registry<sys::SReceiveCallbackDataC> as *mut c_void;
+        unsafe {
+            sys::eCAL_Sub_AddReceiveCallbackC(self.raw, Some(Self::receive_trampoline), ctx) != 0
+        }
+    }
+
+    fn rem_receive_callback(&self) {
+        unsafe { sys::eCAL_Sub_RemReceiveCallback(self.raw) };
+    }
+
+    unsafe extern "C" fn event_trampoline(
+        _topic_name: *const c_char,
+        data: *const sys::SSubEventCallbackDataC,
+        ctx: *mut c_void,
+    ) {
+        let events = &*(ctx as *const Registry<SubscriberEvent>);
+        callback::catch_unwind("Subscriber event callback", || {
+            if let Some(event) = SubscriberEvent::from_sys(&*data) {
+                events.dispatch(&event);
+            }
+        });
+    }
+
+    fn add_event_callbacks(&self) -> bool {
+        let ctx = &self.events as *const Registry<SubscriberEvent> as *mut c_void;
+        for (added, kind) in SubscriberEvent::KINDS.iter().enumerate() {
+            let status = unsafe {
+                sys::eCAL_Sub_AddEventCallbackC(
+                    self.raw,
+                    (*kind).into(),
+                    Some(Self::event_trampoline),
+                    ctx,
+                )
+            };
+            if status == 0 {
+                for kind in &SubscriberEvent::KINDS[..added] {
+                    unsafe { sys::eCAL_Sub_RemEventCallback(self.raw, (*kind).into()) };
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    fn rem_event_callbacks(&self) {
+        for kind in SubscriberEvent::KINDS.iter() {
+            unsafe { sys::eCAL_Sub_RemEventCallback(self.raw, (*kind).into()) };
+        }
+    }
+}
+
+impl Drop for SubscriberHandle {
+    fn drop(&mut self) {
+        // Destroying the subscriber stops all callbacks, after which the remaining
+        // closures are freed together with the registries.
+        unsafe {
+            sys::eCAL_Sub_Destroy(self.raw);
+        }
+    }
+}
+
+unsafe impl Send for SubscriberHandle {}
+unsafe impl Sync for SubscriberHandle {}
+
+/// Payload buffer allocated by eCAL, freed again on drop.
+struct EcalBuffer {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl EcalBuffer {
+    /// Calls an eCAL getter with `ECAL_ALLOCATE_4ME`, taking ownership of its result.
+    fn alloc(get: impl FnOnce(*mut c_void, c_int) -> c_int) -> Option<Self> {
+        let mut ptr = ptr::null_mut::<c_void>();
+        let len = get(
+            &mut ptr as *mut *mut c_void as *mut c_void,
+            sys::ECAL_ALLOCATE_4ME as c_int,
+        );
+        let buffer = EcalBuffer {
+            ptr,
+            len: len.max(0) as usize,
+        };
+        if buffer.ptr.is_null() || buffer.len == 0 {
+            None
+        } else {
+            Some(buffer)
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for EcalBuffer {
+    fn drop(&mut self) {
+        if self.ptr.is_null() {
+            return;
+        }
+        log::trace!("Freeing recv buffer");
+        unsafe {
+            sys::eCAL_FreeMem(self.ptr);
+        }
+    }
+}
+
+/// Subscriber receiving untyped payloads, e.g. for relays, recorders and gateways.
+///
+/// The topic type and description are passed to eCAL as given.
+pub struct RawSubscriber {
+    handle: Arc<SubscriberHandle>,
+    publishers: Arc<Connections>,
+    types: Arc<TypeChecker>,
+}
+
+impl RawSubscriber {
+    pub fn new(topic_name: &str, topic_type: &str, topic_description: &[u8]) -> Result<Self> {
+        Self::builder(topic_name)
+            .topic_type(topic_type)
+            .topic_description(topic_description)
+            .build()
+    }
+
+    pub fn builder(topic_name: &str) -> SubscriberBuilder<Self> {
+        SubscriberBuilder::new(topic_name)
+    }
+
+    /// Sets the quality of service policies of this subscriber.
+    ///
+    /// eCAL may reject changes once the topic is created, see [`SubscriberBuilder::qos`].
+    pub fn set_qos(&mut self, qos: Qos) -> Result<()> {
+        let status = unsafe { sys::eCAL_Sub_SetQOS(self.handle.raw, qos.into()) };
+        if status == 0 {
+            Err(CalError::SettingRejected("qos"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the quality of service policies currently used by this subscriber.
+    pub fn qos(&self) -> Result<Qos> {
+        let mut qos = sys::SReaderQOSC::default();
+        let status = unsafe { sys::eCAL_Sub_GetQOS(self.handle.raw, &mut qos) };
+        if status == 0 {
+            Err(CalError::SettingRejected("qos"))
+        } else {
+            Ok(qos.into())
+        }
+    }
+
+    /// Sets the time after which a [`SubscriberEventKind::Timeout`] event is reported
+    /// if no message arrived.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
+        let timeout = c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX);
+        let status = unsafe { sys::eCAL_Sub_SetTimeout(self.handle.raw, timeout) };
+        if status == 0 {
+            Err(CalError::SettingRejected("timeout"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Only receives messages from publishers whose [id](Publisher::set_id) is in `ids`.
+    ///
+    /// An empty slice removes the filter again.
+    pub fn set_id_filter(&mut self, ids: &[i64]) -> Result<()> {
+        // eCAL resets the filter on a null array.
+        let id_array = if ids.is_empty() {
+            ptr::null()
+        } else {
+            ids.as_ptr() as *const c_longlong
+        };
+        let status = unsafe { sys::eCAL_Sub_SetID(self.handle.raw, id_array, ids.len() as c_int) };
+        if status == 0 {
+            Err(CalError::SettingRejected("id_filter"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Blocks until a publisher is connected, returning `false` if none connected
+    /// within `timeout`.
+    ///
+    /// Publishers are counted from connect and disconnect events, which are only
+    /// received once the subscriber was created. A publisher connecting in the
+    /// short window before that is not noticed until it reconnects.
+    pub fn wait_for_publisher(&self, timeout: Duration) -> bool {
+        self.publishers.wait(timeout)
+    }
+
+    /// Async version of [`wait_for_publisher`](#method.wait_for_publisher).
+    #[cfg(feature = "async")]
+    pub fn wait_for_publisher_async(&self, timeout: Duration) -> WaitForConnection {
+        WaitForConnection::new(self.publishers.clone(), timeout)
+    }
+
+    /// Registers `callback` for the connect, disconnect, drop, timeout and corruption
+    /// events of this subscriber.
+    ///
+    /// The callback runs on an eCAL thread until the returned guard is dropped and
+    /// must not drop callback guards of this subscriber itself.
+    pub fn on_event<F>(&self, mut callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(SubscriberEvent) + Send + 'static,
+    {
+        callback::listen(
+            &self.handle,
+            |handle| &handle.events,
+            SubscriberHandle::add_event_callbacks,
+            SubscriberHandle::rem_event_callbacks,
+            Box::new(move |event| callback(*event)),
+        )
+        .ok_or(CalError::CallbackRegistrationFailed)
+    }
+
+    fn receive(&self, timeout: c_int) -> Result<(EcalBuffer, i64), RecvError> {
+        if !ok() {
+            return Err(RecvError::Closed);
+        }
+
+        let mut buf = ptr::null_mut::<c_void>();
+        let buf_len = sys::ECAL_ALLOCATE_4ME as i32;
+        let mut time = 0;
+
+        let bytes_received = unsafe {
+            sys::eCAL_Sub_Receive(self.handle.raw, &mut buf, buf_len, &mut time, timeout)
+        };
+
+        if bytes_received > 0 {
+            let buffer = EcalBuffer {
+                ptr: buf,
+                len: bytes_received as usize,
+            };
+            match self.types.refused() {
+                Some(mismatch) => Err(RecvError::TypeMismatch(mismatch)),
+                None => Ok((buffer, time)),
+            }
+        } else {
+            log::trace!("Subscriber timeout");
+            if !buf.is_null() {
+                log::warn!("Non-null pointer returned from recv, but bytes_received was 0!");
+            }
+            if ok() {
+                Err(RecvError::Timeout)
+            } else {
+                Err(RecvError::Closed)
+            }
+        }
+    }
+
+    fn _recv(&self, timeout: c_int) -> Result<Received<Vec<u8>>, RecvError> {
+        let (buffer, time) = self.receive(timeout)?;
+        let info = SampleInfo {
+            time,
+            clock: None,
+            id: None,
+            size: buffer.len,
+        };
+        Ok(Received {
+            msg: buffer.as_slice().to_vec(),
+            info,
+        })
+    }
+
+    /// Blocks until a payload arrives.
+    pub fn recv(&self) -> Result<Received<Vec<u8>>, RecvError> {
+        log::trace!("RawSubscriber::recv");
+        self._recv(-1)
+    }
+
+    /// Waits at most `timeout` for a payload, failing with [`RecvError::Timeout`].
+    pub fn try_recv(&self, timeout: Duration) -> Result<Received<Vec<u8>>, RecvError> {
+        log::trace!("RawSubscriber::try_recv");
+        let timeout = c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX);
+        self._recv(timeout)
+    }
+
+    /// Registers `callback` for every payload received by this subscriber.
+    ///
+    /// The payload is borrowed from eCAL for the duration of the call. The callback
+    /// runs on an eCAL thread until the returned guard is dropped, so replacing it
+    /// means registering the new callback and dropping the old guard. While any
+    /// callback is registered, eCAL hands payloads to the callbacks instead of
+    /// [`recv`](#method.recv).
+    pub fn on_recv<F>(&self, mut callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(Received<&[u8]>) + Send + 'static,
+    {
+        self.listen(Box::new(move |data| {
+            let bytes = unsafe { slice::from_raw_parts(data.buf as *const u8, data.size as usize) };
+            log::trace!("Received {} bytes", bytes.len());
+            callback(Received {
+                msg: bytes,
+                info: SampleInfo::from_sys(data),
+            });
+        }))
+    }
+
+    /// Reports [`TypeMismatch`]es found on connect, see [`SubscriberBuilder::type_check`].
+    pub fn on_type_mismatch<F>(&self, mut callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(TypeMismatch) + Send + 'static,
+    {
+        Ok(self
+            .types
+            .listen(Box::new(move |mismatch| callback(mismatch.clone()))))
+    }
+
+    /// The mismatch found when the last publisher connected, if any.
+    pub fn type_mismatch(&self) -> Option<TypeMismatch> {
+        self.types.mismatch()
+    }
+
+    fn listen(
+        &self,
+        mut listener: callback::Listener<sys::SReceiveCallbackDataC>,
+    ) -> Result<CallbackGuard> {
+        let types = self.types.clone();
+        callback::listen(
+            &self.handle,
+            |handle| &handle.received,
+            SubscriberHandle::add_receive_callback,
+            SubscriberHandle::rem_receive_callback,
+            Box::new(move |data| match types.refused() {
+                Some(mismatch) => log::debug!("Refused payload: {}", mismatch),
+                None => listener(data),
+            }),
+        )
+        .ok_or(CalError::CallbackRegistrationFailed)
+    }
+}
+
+pub struct Subscriber<T, D> {
+    raw: RawSubscriber,
+    _ty: PhantomData<T>,
+    _deserializer: PhantomData<D>,
+}
+
+impl<T, D> Subscriber<T, D>
+where
+    D: format::Format + for<'a> format::Deserializer<'a, T>,
+{
+    pub fn new(topic_name: &str) -> Result<Self> {
+        Self::builder(topic_name).build()
+    }
+
+    pub fn builder(topic_name: &str) -> SubscriberBuilder<Self> {
+        SubscriberBuilder::new(topic_name)
+    }
+
+    /// The untyped subscriber this subscriber receives through.
+    pub fn raw(&self) -> &RawSubscriber {
+        &self.raw
+    }
+
+    /// Sets the quality of service policies of this subscriber.
+    pub fn set_qos(&mut self, qos: Qos) -> Result<()> {
+        self.raw.set_qos(qos)
+    }
+
+    /// Returns the quality of service policies currently used by this subscriber.
+    pub fn qos(&self) -> Result<Qos> {
+        self.raw.qos()
+    }
+
+    /// Sets the time after which a [`SubscriberEventKind::Timeout`] event is reported
+    /// if no message arrived.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
+        self.raw.set_timeout(timeout)
+    }
+
+    /// Only receives messages from publishers whose [id](Publisher::set_id) is in `ids`.
+    ///
+    /// An empty slice removes the filter again.
+    pub fn set_id_filter(&mut self, ids: &[i64]) -> Result<()> {
+        self.raw.set_id_filter(ids)
+    }
+
+    /// Blocks until a publisher is connected, returning `false` if none connected
+    /// within `timeout`.
+    ///
+    /// Publishers are counted from connect and disconnect events, which are only
+    /// received once the subscriber was created. A publisher connecting in the
+    /// short window before that is not noticed until it reconnects.
+    pub fn wait_for_publisher(&self, timeout: Duration) -> bool {
+        self.raw.wait_for_publisher(timeout)
+    }
+
+    /// Reports [`TypeMismatch`]es found on connect, see [`SubscriberBuilder::type_check`].
+    pub fn on_type_mismatch<F>(&self, callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(TypeMismatch) + Send + 'static,
+    {
+        self.raw.on_type_mismatch(callback)
+    }
+
+    /// The mismatch found when the last publisher connected, if any.
+    pub fn type_mismatch(&self) -> Option<TypeMismatch> {
+        self.raw.type_mismatch()
+    }
+
+    /// Async version of [`wait_for_publisher`](#method.wait_for_publisher).
+    #[cfg(feature = "async")]
+    pub fn wait_for_publisher_async(&self, timeout: Duration) -> WaitForConnection {
+        self.raw.wait_for_publisher_async(timeout)
+    }
+
+    /// Registers `callback` for the connect, disconnect, drop, timeout and corruption
+    /// events of this subscriber.
+    ///
+    /// The callback runs on an eCAL thread until the returned guard is dropped and
+    /// must not drop callback guards of this subscriber itself.
+    pub fn on_event<F>(&self, callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(SubscriberEvent) + Send + 'static,
+    {
+        self.raw.on_event(callback)
+    }
+
+    fn _recv(&self, timeout: c_int) -> Result<Received<T>, RecvError> {
+        let (buffer, time) = self.raw.receive(timeout)?;
+        // The message owns its data, so it outlives the buffer freed on return.
+        Self::decode(buffer.as_slice(), time)
+    }
+
+    /// Decodes a payload taken with `recv` or `try_recv`, which eCAL only tells the
+    /// send time.
+    fn decode(bytes: &[u8], time: i64) -> Result<Received<T>, RecvError> {
+        let info = SampleInfo {
+            time,
+            clock: None,
+            id: None,
+            size: bytes.len(),
+        };
+        D::deserialize(bytes)
+            .map(|msg| Received { msg, info })
+            .map_err(|error| {
+                log::error!("Failed to decode message: {}", error);
+                RecvError::Decode {
+                    error,
+                    len: bytes.len(),
+                }
+            })
+    }
+
+    /// Blocks until a message arrives.
+    pub fn recv(&self) -> Result<Received<T>, RecvError> {
+        log::trace!("Subscriber::recv");
+        self._recv(-1)
+    }
+
+    /// Waits at most `timeout` for a message, failing with [`RecvError::Timeout`].
+    pub fn try_recv(&self, timeout: Duration) -> Result<Received<T>, RecvError> {
+        log::trace!("Subscriber::try_recv");
+        let timeout = c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX);
+        self._recv(timeout)
+    }
+
+    /// Registers `callback` for every message received by this subscriber.
+    ///
+    /// The callback runs on an eCAL thread until the returned guard is dropped, so
+    /// replacing it means registering the new callback and dropping the old guard.
+    /// While any callback is registered, eCAL hands messages to the callbacks
+    /// instead of [`recv`](#method.recv).
+    pub fn on_recv<F>(&self, mut callback: F) -> Result<CallbackGuard>
+    where
+        F: FnMut(Received<T>) + Send + 'static,
+        T: 'static,
+        D: 'static,
+    {
+        self.raw.listen(Box::new(move |data| {
+            let bytes = unsafe { slice::from_raw_parts(data.buf as *const u8, data.size as usize) };
+
+            match D::deserialize(bytes) {
+                Ok(msg) => {
+                    log::trace!("Received {} bytes", bytes.len());
+                    callback(Received {
+                        msg,
+                        info: SampleInfo::from_sys(data),
+                    });
+                }
+                Err(err) => log::error!("Failed to decode message: {}", err),
+            }
+        }))
+    }
+
+    /// Turns this subscriber into a [`channel::Receiver`] backed by a queue of `capacity`
+    /// messages, applying `policy` when it is full.
+    ///
+    /// Messages are decoded on eCAL's receive thread and handed over through the queue.
+    /// A `capacity` of zero is rejected with [`CalError::ZeroCapacity`].
+    pub fn into_channel(
+        self,
+        capacity: usize,
+        policy: channel::DropPolicy,
+    ) -> Result<channel::Receiver<T>>
+    where
+        T: Send + 'static,
+        D: 'static,
+    {
+        let capacity = NonZeroUsize::new(capacity).ok_or(CalError::ZeroCapacity)?;
+        let queue = Arc::new(channel::Queue::new(capacity, policy));
+        let guard = {
+            let queue = queue.clone();
+            self.on_recv(move |received| queue.push(received))?
+        };
+        Ok(channel::Receiver::new(queue, guard, self.raw))
+    }
+
+    /// Turns this subscriber into a [`Stream`](futures_core::Stream) of the messages it
+    /// receives.
+    ///
+    /// Messages are decoded on eCAL's receive thread and queued until the stream is polled.
+    /// At most `capacity` messages are queued, `policy` decides what happens to further
+    /// ones. Fails with [`CalError::ZeroCapacity`] if `capacity` is 0.
+    #[cfg(feature = "async")]
+    pub fn into_stream(
+        self,
+        capacity: usize,
+        policy: channel::DropPolicy,
+    ) -> Result<stream::SubscriberStream<T>>
+    where
+        T: Send + 'static,
+        D: 'static,
+    {
+        let capacity = NonZeroUsize::new(capacity).ok_or(CalError::ZeroCapacity)?;
+        let inbox = stream::Inbox::new(capacity, policy, &channel::OPEN);
+        let guard = {
+            let inbox = inbox.clone();
+            self.on_recv(move |received| inbox.push(received))?
+        };
+        Ok(stream::SubscriberStream::new(inbox, guard, self.raw))
+    }
+}
+
+/// Declarative setup of a [`Subscriber`] or [`RawSubscriber`].
+///
+/// All settings are applied while building, quality of service before the topic is
+/// created and everything else right after it. The first rejected setting is
+/// reported as [`CalError::SettingRejected`].
+pub struct SubscriberBuilder<S> {
+    topic_name: String,
+    topic_type: Option<String>,
+    topic_description: Option<Vec<u8>>,
+    qos: Option<Qos>,
+    timeout: Option<Duration>,
+    id_filter: Option<Vec<i64>>,
+    type_check: TypeCheck,
+    _subscriber: PhantomData<S>,
+}
+
+impl<S> SubscriberBuilder<S> {
+    pub fn new(topic_name: &str) -> Self {
+        SubscriberBuilder {
+            topic_name: topic_name.to_string(),
+            topic_type: None,
+            topic_description: None,
+            qos: None,
+            timeout: None,
+            id_filter: None,
+            type_check: TypeCheck::Off,
+            _subscriber: Default::default(),
+        }
+    }
+
+    /// Overrides the topic type registered with eCAL.
+    pub fn topic_type(mut self, topic_type: &str) -> Self {
+        self.topic_type = Some(topic_type.to_string());
+        self
+    }
+
+    /// Overrides the topic description registered with eCAL.
+    pub fn topic_description(mut self, topic_description: &[u8]) -> Self {
+        self.topic_description = Some(topic_description.to_vec());
+        self
+    }
+
+    pub fn qos(mut self, qos: Qos) -> Self {
+        self.qos = Some(qos);
+        self
+    }
+
+    /// See [`Subscriber::set_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [`Subscriber::set_id_filter`].
+    pub fn id_filter(mut self, ids: &[i64]) -> Self {
+        self.id_filter = Some(ids.to_vec());
+        self
+    }
+
+    /// Compares the topic type and description with the publishers' on connect.
+    pub fn type_check(mut self, mode: TypeCheck) -> Self {
+        self.type_check = mode;
+        self
+    }
+
+    fn create(self, topic_type: String, topic_description: Vec<u8>) -> Result<RawSubscriber> {
+        let topic_type = self.topic_type.unwrap_or(topic_type);
+        let topic_description = self.topic_description.unwrap_or(topic_description);
+
+        let handle = Arc::new(SubscriberHandle {
+            raw: unsafe { sys::eCAL_Sub_New() },
+            events: Registry::new(),
+            received: Registry::new(),
+        });
+        let c_topic_name = ffi::CString::new(self.topic_name.as_str())?;
+        let c_topic_type = ffi::CString::new(topic_type.as_str())?;
+        let types = TypeChecker::new(
+            handle.raw,
+            c_topic_name.clone(),
+            topic_type,
+            topic_description.clone(),
+            self.type_check,
+        );
+        // The C API cannot tell whether a publisher is connected, so only the
+        // connect and disconnect events count.
+        let publishers = Connections::new(|| false);
+        let mut subscriber = RawSubscriber {
+            handle,
+            publishers: Arc::new(publishers),
+            types: Arc::new(types),
+        };
+        if let Some(qos) = self.qos {
+            subscriber.set_qos(qos)?;
+        }
+
+        let status = unsafe {
+            sys::eCAL_Sub_Create(
+                subscriber.handle.raw,
+                c_topic_name.as_ptr(),
+                c_topic_type.as_ptr(),
+                topic_description.as_ptr() as *const c_char,
+                topic_description.len() as c_int,
+            )
+        };
+        if status == 0 {
+            return Err(CalError::SubscriberCreationFailed(self.topic_name));
+        }
+
+        let publishers = subscriber.publishers.clone();
+        subscriber
+            .on_event(move |event| match event.kind {
+                SubscriberEventKind::Connected => publishers.connected(),
+                SubscriberEventKind::Disconnected => publishers.disconnected(),
+                _ => {}
+            })?
+            .forget();
+        if self.type_check != TypeCheck::Off {
+            let types = subscriber.types.clone();
+            subscriber
+                .on_event(move |event| {
+                    if event.kind == SubscriberEventKind::Connected {
+                        types.check();
+                    }
+                })?
+                .forget();
+        }
+
+        if let Some(timeout) = self.timeout {
+            subscriber.set_timeout(timeout)?;
+        }
+        if let Some(ids) = self.id_filter {
+            subscriber.set_id_filter(&ids)?;
+        }
+
+        Ok(subscriber)
+    }
+}
+
+impl SubscriberBuilder<RawSubscriber> {
+    /// Creates the subscriber. Without an explicit topic type, an empty one is registered.
+    pub fn build(self) -> Result<RawSubscriber> {
+        self.create(String::new(), Vec::new())
+    }
+}
+
+impl<T, D> SubscriberBuilder<Subscriber<T, D>>
+where
+    D: format::Format,
+{
+    /// Creates the subscriber, registering the type and description of `D` unless overridden.
+    pub fn build(self) -> Result<Subscriber<T, D>> {
+        let description = D::topic_description().unwrap_or_default();
+        let raw = self.create(D::topic_type(), description.into_bytes())?;
+        Ok(Subscriber {
+            raw,
+            _ty: Default::default(),
+            _deserializer: Default::default(),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NodeState {
+    Healthy,
+    Critical,
+    Failed,
+    Unknown,
+    Warning,
+}
+
+impl From<NodeState> for sys::eCAL_Process_eSeverity {
+    fn from(state: NodeState) -> Self {
+        use sys::eCAL_Process_eSeverity::*;
+        use NodeState::*;
+        match state {
+            Healthy => proc_sev_healthy,
+            Critical => proc_sev_critical,
+            Failed => proc_sev_failed,
+            Unknown => proc_sev_unknown,
+            Warning => proc_sev_warning,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SeverityLevel {
+    Level1,
+    Level2,
+    Level3,
+    Level4,
+    Level5,
+}
+
+impl From<SeverityLevel> for sys::eCAL_Process_eSeverity_Level {
+    fn from(level: SeverityLevel) -> Self {
+        use sys::eCAL_Process_eSeverity_Level::*;
+        use SeverityLevel::*;
+        match level {
+            Level1 => proc_sev_level1,
+            Level2 => proc_sev_level2,
+            Level3 => proc_sev_level3,
+            Level4 => proc_sev_level4,
+            Level5 => proc_sev_level5,
+        }
+    }
+}
+
+/// Level of an eCAL log message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+    Fatal,
+    Debug1,
+    Debug2,
+    Debug3,
+    Debug4,
+}
+
+impl LogLevel {
+    /// Converts the level value eCAL puts into log messages.
+    pub fn from_raw(level: i32) -> Option<Self> {
+        use sys::eCAL_Logging_eLogLevel::*;
+        use LogLevel::*;
+        let level = match level {
+            l if l == log_level_info as i32 => Info,
+            l if l == log_level_warning as i32 => Warning,
+            l if l == log_level_error as i32 => Error,
+            l if l == log_level_fatal as i32 => Fatal,
+            l if l == log_level_debug1 as i32 => Debug1,
+            l if l == log_level_debug2 as i32 => Debug2,
+            l if l == log_level_debug3 as i32 => Debug3,
+            l if l == log_level_debug4 as i32 => Debug4,
+            _ => return None,
+        };
+        Some(level)
+    }
+}
+
+impl From<LogLevel> for sys::eCAL_Logging_eLogLevel {
+    fn from(level: LogLevel) -> Self {
+        use sys::eCAL_Logging_eLogLevel::*;
+        use LogLevel::*;
+        match level {
+            Info => log_level_info,
+            Warning => log_level_warning,
+            Error => log_level_error,
+            Fatal => log_level_fatal,
+            Debug1 => log_level_debug1,
+            Debug2 => log_level_debug2,
+            Debug3 => log_level_debug3,
+            Debug4 => log_level_debug4,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Cal {
+    status_msg: ffi::CString,
+}
+
+impl Cal {
+    pub fn new(unit_name: &str) -> Result<Self> {
+        Self::with_components(unit_name, sys::eCAL_Init_Default)
+    }
+
+    /// Initializes eCAL like [`new`](#method.new) and also starts the monitoring
+    /// layer, which is needed to inspect other processes with [`monitoring`].
+    pub fn with_monitoring(unit_name: &str) -> Result<Self> {
+        Self::with_components(
+            unit_name,
+            sys::eCAL_Init_Default | sys::eCAL_Init_Monitoring,
+        )
+    }
+
+    fn with_components(unit_name: &str, components: u32) -> Result<Self> {
+        initialize(unit_name, components).and_then(|_| {
+            let mut cal = Cal::default();
+            cal.set_state(NodeState::Healthy, SeverityLevel::Level1, "ok")?;
+            Ok(cal)
+        })
+    }
+
+    /// Sets the process state and severity level.
+    /// Can fail if the status message is unable to be
+    /// converted to a CString.
+    pub fn set_state(&mut self, state: NodeState, level: SeverityLevel, info: &str) -> Result<()> {
+        self.status_msg = ffi::CString::new(info)?;
+        unsafe { sys::eCAL_Process_SetState(state.into(), level.into(), self.status_msg.as_ptr()) };
+        Ok(())
+    }
+}
+
+impl Drop for Cal {
+    fn drop(&mut self) {
+        finalize();
+    }
+}
+
+fn initialize(unit_name: &str, components: u32) -> Result<()> {
     let mut args = env::args()
         .map(|arg| ffi::CString::new(arg).expect("Failed to build CString from arg"))
         .collect::<Vec<ffi::CString>>();
@@ -652,19 +2088,13 @@ fn initialize(unit_name: &str) -> Result<()> {
 
     let c_unit_name = ffi::CString::new(unit_name).expect("Failed to build CString from unit_name");
 
-    let status = unsafe {
-        sys::eCAL_Initialize(
-            argc,
-            argv.as_mut_ptr(),
-            c_unit_name.as_ptr(),
-            sys::eCAL_Init_Default,
-        )
-    };
+    let status =
+        unsafe { sys::eCAL_Initialize(argc, argv.as_mut_ptr(), c_unit_name.as_ptr(), components) };
 
     match status {
         -1 => {
             log::error!("Failed to initialize eCAL");
-            return Err(CalError::InitializationFailed.into());
+            return Err(CalError::InitializationFailed);
         }
         0 => log::info!("eCAL initiailized as '{}'.", unit_name),
         1 => log::warn!("eCAL was already initialized."),
@@ -678,6 +2108,9 @@ fn initialize(unit_name: &str) -> Result<()> {
 }
 
 fn finalize() {
+    // Releases receive callbacks blocked on a full queue first, eCAL waits for
+    // its receive threads while finalizing.
+    channel::close_all();
     unsafe {
         log::debug!("Finalizing eCAL system.");
         let _ = sys::eCAL_Finalize(sys::eCAL_Init_All);
@@ -702,7 +2135,7 @@ mod tests {
     #[test]
     fn ecal_init_and_finalize() {
         let _ = ::env_logger::try_init();
-        assert!(super::initialize("kcal_init_test").is_ok());
+        assert!(super::initialize("kcal_init_test", super::sys::eCAL_Init_Default).is_ok());
         super::sleep(std::time::Duration::from_millis(10));
         super::finalize();
     }
@@ -735,6 +2168,192 @@ mod tests {
             assert!(super::ok());
         }
     }
+
+    #[test]
+    fn qos_sys_round_trip() {
+        use super::{History, Qos, Reliability};
+        let qos = Qos {
+            history: History::KeepLast(16),
+            reliability: Reliability::BestEffort,
+        };
+        assert_eq!(Qos::from(crate::sys::SWriterQOSC::from(qos)), qos);
+        let qos = Qos {
+            history: History::KeepAll,
+            reliability: Reliability::Reliable,
+        };
+        assert_eq!(Qos::from(crate::sys::SReaderQOSC::from(qos)), qos);
+    }
+
+    #[test]
+    fn bandwidth_to_c_long() {
+        use super::Bandwidth;
+        use std::os::raw::c_long;
+        assert_eq!(c_long::from(Bandwidth::Unlimited), -1);
+        assert_eq!(c_long::from(Bandwidth::BytesPerSecond(0)), 0);
+        assert_eq!(
+            c_long::from(Bandwidth::BytesPerSecond(1_000_000)),
+            1_000_000
+        );
+        assert_eq!(
+            c_long::from(Bandwidth::BytesPerSecond(c_long::MAX as u64)),
+            c_long::MAX
+        );
+        assert_eq!(
+            c_long::from(Bandwidth::BytesPerSecond(c_long::MAX as u64 + 1)),
+            c_long::MAX
+        );
+        assert_eq!(
+            c_long::from(Bandwidth::BytesPerSecond(u64::MAX)),
+            c_long::MAX
+        );
+    }
+
+    /// Encodes strings as their UTF-8 bytes.
+    struct Utf8;
+
+    impl super::format::Format for Utf8 {
+        fn topic_type() -> String {
+            "utf8".to_owned()
+        }
+
+        fn topic_description() -> Option<String> {
+            None
+        }
+    }
+
+    impl<'a> super::format::Deserializer<'a, String> for Utf8 {
+        fn deserialize(buffer: &'a [u8]) -> super::format::Result<String> {
+            Ok(String::from_utf8(buffer.to_vec())?)
+        }
+    }
+
+    impl super::format::Serializer<String> for Utf8 {
+        fn serialize(message: &String, buffer: &mut Vec<u8>) -> super::format::Result<()> {
+            buffer.extend_from_slice(message.as_bytes());
+            Ok(())
+        }
+
+        fn encoded_len(message: &String) -> Option<usize> {
+            Some(message.len())
+        }
+    }
+
+    /// Encodes `msg` like `Publisher::send`, returning the payload and its address.
+    fn encode(buffer: &std::sync::Mutex<Vec<u8>>, msg: &str) -> (Vec<u8>, *const u8) {
+        let mut sent = None;
+        super::Publisher::<String, Utf8>::encode(buffer, &msg.to_owned(), |payload| {
+            sent = Some((payload.to_vec(), payload.as_ptr()));
+            Ok(())
+        })
+        .unwrap();
+        sent.unwrap()
+    }
+
+    #[test]
+    fn publisher_events_from_sys() {
+        use super::{sys, PublisherEvent, PublisherEventKind};
+        let data = sys::SPubEventCallbackDataC {
+            type_: sys::eCAL_Publisher_Event::pub_event_disconnected,
+            time: 1_500,
+            clock: 3,
+        };
+        assert_eq!(
+            PublisherEvent::from_sys(&data),
+            Some(PublisherEvent {
+                kind: PublisherEventKind::Disconnected,
+                time: 1_500,
+                clock: 3,
+            })
+        );
+        for &kind in PublisherEvent::KINDS.iter() {
+            let data = sys::SPubEventCallbackDataC {
+                type_: kind.into(),
+                ..Default::default()
+            };
+            assert_eq!(
+                PublisherEvent::from_sys(&data).map(|event| event.kind),
+                Some(kind)
+            );
+        }
+        assert_eq!(
+            PublisherEvent::from_sys(&sys::SPubEventCallbackDataC::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn subscriber_events_from_sys() {
+        use super::{sys, SubscriberEvent, SubscriberEventKind};
+        let data = sys::SSubEventCallbackDataC {
+            type_: sys::eCAL_Subscriber_Event::sub_event_corrupted,
+            time: 1_500,
+            clock: 3,
+        };
+        assert_eq!(
+            SubscriberEvent::from_sys(&data),
+            Some(SubscriberEvent {
+                kind: SubscriberEventKind::Corrupted,
+                time: 1_500,
+                clock: 3,
+            })
+        );
+        for &kind in SubscriberEvent::KINDS.iter() {
+            let data = sys::SSubEventCallbackDataC {
+                type_: kind.into(),
+                ..Default::default()
+            };
+            assert_eq!(
+                SubscriberEvent::from_sys(&data).map(|event| event.kind),
+                Some(kind)
+            );
+        }
+        assert_eq!(
+            SubscriberEvent::from_sys(&sys::SSubEventCallbackDataC::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn send_buffer_is_reused_and_cleared() {
+        let buffer = std::sync::Mutex::new(Vec::new());
+        let (first, first_ptr) = encode(&buffer, "ping");
+        assert_eq!(first, b"ping");
+        assert!(buffer.lock().unwrap().capacity() >= 4);
+
+        let (second, second_ptr) = encode(&buffer, "pi");
+        assert_eq!(second, b"pi");
+        assert_eq!(second_ptr, first_ptr);
+    }
+
+    #[test]
+    fn busy_send_buffer_falls_back_to_a_fresh_one() {
+        let buffer = std::sync::Mutex::new(b"pong".to_vec());
+        let shared = buffer.lock().unwrap();
+        let (payload, ptr) = encode(&buffer, "ping");
+        assert_eq!(payload, b"ping");
+        assert_ne!(ptr, shared.as_ptr());
+        assert_eq!(*shared, b"pong");
+    }
+
+    #[test]
+    fn received_messages_carry_send_time_only() {
+        use super::Subscriber;
+        let received = Subscriber::<String, Utf8>::decode(b"ping", 1_500).unwrap();
+        assert_eq!(received.msg, "ping");
+        assert_eq!(received.info.time, 1_500);
+        assert_eq!(received.info.size, 4);
+        assert_eq!(received.info.clock, None);
+        assert_eq!(received.info.id, None);
+    }
+
+    #[test]
+    fn decode_error_reports_payload_size() {
+        use super::{RecvError, Subscriber};
+        match Subscriber::<String, Utf8>::decode(&[0xff, 0xfe, 0xfd], 0) {
+            Err(RecvError::Decode { len, .. }) => assert_eq!(len, 3),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
 }
 
 unsafe impl<T, S> Send for Publisher<T, S> {}
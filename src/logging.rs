@@ -0,0 +1,256 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Forwarding of `log` records to eCAL's logging, where eCAL Monitor shows them.
+//!
+//! With the `tracing` feature, [`EcalLayer`] does the same for `tracing` events.
+
+use std::{
+    ffi::CString,
+    fmt::Write as _,
+    sync::{Mutex, PoisonError},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{sys, LogLevel};
+
+#[cfg(feature = "tracing")]
+mod layer;
+#[cfg(feature = "tracing")]
+pub use layer::EcalLayer;
+
+/// eCAL logs every message with the process wide current level, so setting the
+/// level and logging have to happen together.
+static ECAL_LOG: Mutex<()> = Mutex::new(());
+
+/// A `log` backend writing to eCAL's logging.
+///
+/// `Debug` records are logged as [`LogLevel::Debug1`] and `Trace` records as
+/// [`LogLevel::Debug4`] unless changed with [`trace_level`](#method.trace_level).
+/// Records can additionally be passed to another logger with [`tee`](#method.tee).
+///
+/// eCAL logs with a process wide level instead of one per message, so a record
+/// of another level than the current one briefly sets its level and restores
+/// the previous one afterwards. Only records of this logger and `EcalLayer` are
+/// serialized with each other. Messages eCAL or C++ code in the same process
+/// log meanwhile may be logged with the level of the record, and a level set
+/// with `eCAL_Logging_SetLogLevel` meanwhile is overwritten with the previous one.
+///
+/// ```no_run
+/// # fn main() -> ecal::Result<()> {
+/// let _cal = ecal::Cal::new("logging_demo")?;
+/// ecal::logging::EcalLogger::new()
+///     .max_level(log::LevelFilter::Debug)
+///     .tee(env_logger::Logger::from_default_env())
+///     .init()
+///     .expect("a logger was already set");
+/// log::info!("shows up in eCAL Monitor");
+/// # Ok(())
+/// # }
+/// ```
+pub struct EcalLogger {
+    max_level: LevelFilter,
+    trace_level: LogLevel,
+    tee: Option<Box<dyn Log>>,
+}
+
+impl EcalLogger {
+    /// Creates a logger forwarding records up to `Info`.
+    pub fn new() -> Self {
+        EcalLogger {
+            max_level: LevelFilter::Info,
+            trace_level: LogLevel::Debug4,
+            tee: None,
+        }
+    }
+
+    /// Sets the most verbose level that is logged.
+    pub fn max_level(mut self, level: LevelFilter) -> Self {
+        self.max_level = level;
+        self
+    }
+
+    /// Sets the eCAL level `Trace` records are logged with.
+    pub fn trace_level(mut self, level: LogLevel) -> Self {
+        self.trace_level = level;
+        self
+    }
+
+    /// Also passes every record up to the [`max_level`](#method.max_level) to `logger`,
+    /// if `logger` is enabled for it.
+    pub fn tee(mut self, logger: impl Log + 'static) -> Self {
+        self.tee = Some(Box::new(logger));
+        self
+    }
+
+    /// Installs the logger as the global `log` backend.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max_level = self.max_level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Default for EcalLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Log for EcalLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.max_level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Some(tee) = self
+            .tee
+            .as_ref()
+            .filter(|tee| tee.enabled(record.metadata()))
+        {
+            tee.log(record);
+        }
+
+        let mut message = String::new();
+        let _ = write!(message, "{}: {}", record.target(), record.args());
+        write(ecal_level(record.level(), self.trace_level), &message);
+    }
+
+    fn flush(&self) {
+        if let Some(tee) = &self.tee {
+            tee.flush();
+        }
+    }
+}
+
+/// The eCAL level records of `level` are logged with, `trace_level` for `Trace`.
+fn ecal_level(level: Level, trace_level: LogLevel) -> LogLevel {
+    match level {
+        Level::Error => LogLevel::Error,
+        Level::Warn => LogLevel::Warning,
+        Level::Info => LogLevel::Info,
+        Level::Debug => LogLevel::Debug1,
+        Level::Trace => trace_level,
+    }
+}
+
+/// Logs `message` with eCAL at `level`.
+fn write(level: LogLevel, message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+
+    let _lock = ECAL_LOG.lock().unwrap_or_else(PoisonError::into_inner);
+    unsafe {
+        let level = level.into();
+        let previous = sys::eCAL_Logging_GetLogLevel();
+        if previous == level {
+            sys::eCAL_Logging_Log(message.as_ptr());
+        } else {
+            sys::eCAL_Logging_SetLogLevel(level);
+            sys::eCAL_Logging_Log(message.as_ptr());
+            sys::eCAL_Logging_SetLogLevel(previous);
+        }
+    }
+}
+
+impl std::fmt::Debug for EcalLogger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EcalLogger")
+            .field("max_level", &self.max_level)
+            .field("trace_level", &self.trace_level)
+            .field("tee", &self.tee.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ecal_level, EcalLogger};
+    use crate::LogLevel;
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone)]
+    struct Collect(Arc<Mutex<Vec<String>>>, LevelFilter);
+
+    impl Default for Collect {
+        fn default() -> Self {
+            Collect(Arc::default(), LevelFilter::Trace)
+        }
+    }
+
+    impl Log for Collect {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.level() <= self.1
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn maps_levels() {
+        let trace_level = LogLevel::Debug3;
+        assert_eq!(ecal_level(Level::Error, trace_level), LogLevel::Error);
+        assert_eq!(ecal_level(Level::Warn, trace_level), LogLevel::Warning);
+        assert_eq!(ecal_level(Level::Info, trace_level), LogLevel::Info);
+        assert_eq!(ecal_level(Level::Debug, trace_level), LogLevel::Debug1);
+        assert_eq!(ecal_level(Level::Trace, trace_level), LogLevel::Debug3);
+    }
+
+    #[test]
+    fn tees_enabled_records() {
+        let collect = Collect::default();
+        let logger = EcalLogger::new()
+            .max_level(LevelFilter::Debug)
+            .tee(collect.clone());
+
+        for level in &[Level::Info, Level::Debug, Level::Trace] {
+            logger.log(
+                &Record::builder()
+                    .level(*level)
+                    .args(format_args!("{}", level))
+                    .build(),
+            );
+        }
+
+        assert_eq!(*collect.0.lock().unwrap(), vec!["INFO", "DEBUG"]);
+    }
+
+    #[test]
+    fn tee_only_gets_records_it_is_enabled_for() {
+        let collect = Collect(Arc::default(), LevelFilter::Warn);
+        let logger = EcalLogger::new().tee(collect.clone());
+
+        for level in &[Level::Error, Level::Warn, Level::Info] {
+            logger.log(
+                &Record::builder()
+                    .level(*level)
+                    .args(format_args!("{}", level))
+                    .build(),
+            );
+        }
+
+        assert_eq!(*collect.0.lock().unwrap(), vec!["ERROR", "WARN"]);
+    }
+}
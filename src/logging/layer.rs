@@ -0,0 +1,272 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+use std::fmt::{self, Write as _};
+
+use tracing_core::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use super::{ecal_level, write};
+use crate::{sys, LogLevel};
+
+/// A `tracing_subscriber::Layer` writing events to eCAL's logging.
+///
+/// Every message starts with the spans the event happened in and their fields,
+/// followed by the target, the message and the event's fields. `DEBUG` events
+/// are logged as [`LogLevel::Debug1`] and `TRACE` events as [`LogLevel::Debug4`]
+/// unless changed with [`trace_level`](#method.trace_level).
+///
+/// Entering the span named with [`core_span`](#method.core_span) starts eCAL's
+/// core timer and leaving it stops the timer, so eCAL Monitor shows how long
+/// one pass through it takes.
+///
+/// ```no_run
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// # fn main() -> ecal::Result<()> {
+/// let _cal = ecal::Cal::new("tracing_demo")?;
+/// let subscriber = tracing_subscriber::registry()
+///     .with(ecal::logging::EcalLayer::new().core_span("main_loop"));
+/// tracing::subscriber::set_global_default(subscriber).expect("a subscriber was already set");
+///
+/// while ecal::ok() {
+///     let _main_loop = tracing::info_span!("main_loop").entered();
+///     tracing::info!(rate = 10, "working");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EcalLayer {
+    trace_level: LogLevel,
+    core_span: Option<&'static str>,
+}
+
+impl EcalLayer {
+    pub fn new() -> Self {
+        EcalLayer {
+            trace_level: LogLevel::Debug4,
+            core_span: None,
+        }
+    }
+
+    /// Sets the eCAL level `TRACE` events are logged with.
+    pub fn trace_level(mut self, level: LogLevel) -> Self {
+        self.trace_level = level;
+        self
+    }
+
+    /// Measures the time spent in spans named `name` with eCAL's core timer.
+    ///
+    /// eCAL has a single core timer per process, so the span should only be
+    /// entered by one thread at a time and not be nested in itself.
+    pub fn core_span(mut self, name: &'static str) -> Self {
+        self.core_span = Some(name);
+        self
+    }
+
+    fn ecal_level(&self, level: &Level) -> LogLevel {
+        let level = match *level {
+            Level::ERROR => log::Level::Error,
+            Level::WARN => log::Level::Warn,
+            Level::INFO => log::Level::Info,
+            Level::DEBUG => log::Level::Debug,
+            Level::TRACE => log::Level::Trace,
+        };
+        ecal_level(level, self.trace_level)
+    }
+
+    fn is_core_span<S>(&self, id: &Id, ctx: &Context<'_, S>) -> bool
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        match (self.core_span, ctx.metadata(id)) {
+            (Some(core_span), Some(metadata)) => metadata.name() == core_span,
+            _ => false,
+        }
+    }
+}
+
+impl Default for EcalLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The rendered fields of a span, kept in its extensions.
+struct SpanFields(String);
+
+impl<S> Layer<S> for EcalLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(SpanFields(fields.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if let Some(SpanFields(rendered)) = extensions.get_mut::<SpanFields>() {
+                let mut fields = Fields {
+                    fields: std::mem::take(rendered),
+                    ..Fields::default()
+                };
+                values.record(&mut fields);
+                *rendered = fields.fields;
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        write(
+            self.ecal_level(event.metadata().level()),
+            &render(event, &ctx),
+        );
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if self.is_core_span(id, &ctx) {
+            unsafe { sys::eCAL_Logging_StartCoreTimer() };
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if self.is_core_span(id, &ctx) {
+            unsafe { sys::eCAL_Logging_StopCoreTimer() };
+        }
+    }
+}
+
+/// Renders the message of `event`, prefixed with the spans it happened in.
+fn render<S>(event: &Event<'_>, ctx: &Context<'_, S>) -> String
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut message = String::new();
+    if let Some(scope) = ctx.event_scope(event) {
+        for span in scope.from_root() {
+            message.push_str(span.name());
+            if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                if !fields.is_empty() {
+                    let _ = write!(message, "{{{}}}", fields);
+                }
+            }
+            message.push(':');
+        }
+        message.push(' ');
+    }
+
+    let mut fields = Fields::default();
+    event.record(&mut fields);
+    let _ = write!(message, "{}: {}", event.metadata().target(), fields.message);
+    if !fields.fields.is_empty() {
+        let _ = write!(message, " {}", fields.fields);
+    }
+    message
+}
+
+/// Renders the `message` field and all other fields as `name=value`.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={:?}", field.name(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, EcalLayer};
+    use crate::LogLevel;
+    use std::sync::{Arc, Mutex};
+    use tracing_core::{Event, Level, Subscriber};
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt},
+        registry::LookupSpan,
+        Layer,
+    };
+
+    /// Keeps the messages the [`EcalLayer`] below it writes to eCAL.
+    struct Rendered(Arc<Mutex<Vec<String>>>);
+
+    impl<S> Layer<S> for Rendered
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(render(event, &ctx));
+        }
+    }
+
+    #[test]
+    fn renders_span_context() {
+        let rendered = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry()
+            .with(EcalLayer::new())
+            .with(Rendered(rendered.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!("outer", id = 7);
+            let _outer = outer.enter();
+            let inner = tracing::info_span!("inner", step = tracing::field::Empty);
+            inner.record("step", "send");
+            let _inner = inner.enter();
+            tracing::trace!(target: "ping", size = 3, "sent {}", "ping");
+        });
+
+        assert_eq!(
+            *rendered.lock().unwrap(),
+            vec!["outer{id=7}:inner{step=\"send\"}: ping: sent ping size=3".to_owned()]
+        );
+    }
+
+    #[test]
+    fn trace_level_is_configurable() {
+        let layer = EcalLayer::new();
+        assert_eq!(layer.ecal_level(&Level::TRACE), LogLevel::Debug4);
+        assert_eq!(layer.ecal_level(&Level::DEBUG), LogLevel::Debug1);
+        let layer = layer.trace_level(LogLevel::Debug2);
+        assert_eq!(layer.ecal_level(&Level::TRACE), LogLevel::Debug2);
+    }
+}
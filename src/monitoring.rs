@@ -0,0 +1,492 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Inspection of the eCAL system through its monitoring layer.
+//!
+//! The monitoring layer has to be started with
+//! [`Cal::with_monitoring`](../struct.Cal.html#method.with_monitoring), otherwise
+//! there is nothing to inspect. Which topics it reports can be narrowed down
+//! with a [`MonitoringFilter`]. The monitoring layer also collects the messages
+//! logged by all processes, see [`logs`].
+
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use prost::Message as _;
+
+use crate::{
+    sys, CalError, EcalBuffer, LogLevel, NodeState, Result, SeverityLevel, TransportLayer,
+};
+
+mod filter;
+#[allow(clippy::enum_variant_names)]
+#[rustfmt::skip]
+mod pb;
+
+pub use filter::{FilterGuard, MonitoringFilter};
+
+/// All hosts, processes, topics and services currently registered with eCAL.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub hosts: Vec<Host>,
+    pub processes: Vec<Process>,
+    pub publishers: Vec<Topic>,
+    pub subscribers: Vec<Topic>,
+    pub services: Vec<Service>,
+    pub clients: Vec<Client>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Host {
+    pub name: String,
+    /// Name of the operating system.
+    pub os: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub host: String,
+    pub pid: i32,
+    pub name: String,
+    pub unit: String,
+    /// Command line the process was started with.
+    pub params: String,
+    pub state: NodeState,
+    /// `None` if the process did not report a severity level.
+    pub severity_level: Option<SeverityLevel>,
+    pub state_info: String,
+    /// eCAL version the process runs with.
+    pub ecal_version: String,
+    /// Incremented with every registration of the process.
+    pub registration_clock: i32,
+}
+
+/// A publisher or subscriber of a topic.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topic {
+    pub host: String,
+    pub pid: i32,
+    pub process: String,
+    pub unit: String,
+    /// eCAL's id of this publisher or subscriber.
+    pub id: String,
+    pub name: String,
+    pub topic_type: String,
+    pub description: Vec<u8>,
+    /// Size of the last message in bytes.
+    pub size: usize,
+    /// Transport layers in use.
+    pub layers: Vec<TransportLayer>,
+    /// Number of connected peers on this host.
+    pub local_connections: usize,
+    /// Number of connected peers on other hosts.
+    pub external_connections: usize,
+    /// Messages lost on their way to a subscriber.
+    pub message_drops: u64,
+    /// Id set with [`Publisher::set_id`](../struct.Publisher.html#method.set_id).
+    pub data_id: i64,
+    /// Number of messages sent or received.
+    pub data_clock: i64,
+    /// Messages sent or received per second.
+    pub data_frequency: f64,
+    /// Incremented with every registration of the topic.
+    pub registration_clock: i32,
+}
+
+/// A [`Server`](../struct.Server.html) offering a service.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Service {
+    pub host: String,
+    pub pid: i32,
+    pub process: String,
+    pub unit: String,
+    /// eCAL's id of this server.
+    pub id: String,
+    pub name: String,
+    /// TCP port the server listens on, `None` if eCAL reported no valid port.
+    pub tcp_port: Option<u16>,
+    pub methods: Vec<Method>,
+    /// Incremented with every registration of the service.
+    pub registration_clock: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Method {
+    pub name: String,
+    pub request_type: String,
+    pub response_type: String,
+    /// Number of calls served.
+    pub call_count: i64,
+}
+
+/// A [`Client`](../struct.Client.html) of a service.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Client {
+    pub host: String,
+    pub pid: i32,
+    pub process: String,
+    pub unit: String,
+    /// eCAL's id of this client.
+    pub id: String,
+    pub service: String,
+    /// Incremented with every registration of the client.
+    pub registration_clock: i32,
+}
+
+/// A message logged by an eCAL process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub time: SystemTime,
+    pub host: String,
+    pub process: String,
+    pub pid: i32,
+    pub unit: String,
+    /// `None` if eCAL reported an unknown level.
+    pub level: Option<LogLevel>,
+    pub content: String,
+}
+
+/// Takes a snapshot of everything the monitoring layer currently knows about.
+pub fn snapshot() -> Result<Snapshot> {
+    let buffer =
+        EcalBuffer::alloc(|buf, len| unsafe { sys::eCAL_Monitoring_GetMonitoring(buf, len) })
+            .ok_or(CalError::MonitoringUnavailable)?;
+    let monitoring = pb::Monitoring::decode(buffer.as_slice())
+        .map_err(|error| CalError::MonitoringDecode(error.into()))?;
+    Ok(monitoring.into())
+}
+
+/// Returns the log messages received from all processes since the last call.
+///
+/// eCAL hands out every message only once, so calling this from several places,
+/// or next to a [`LogPoller`], splits the messages between them.
+pub fn logs() -> Result<Vec<LogRecord>> {
+    // An empty log is encoded as zero bytes, which eCAL reports like a failure.
+    let buffer =
+        match EcalBuffer::alloc(|buf, len| unsafe { sys::eCAL_Monitoring_GetLogging(buf, len) }) {
+            Some(buffer) => buffer,
+            None => return Ok(Vec::new()),
+        };
+    let logging = pb::Logging::decode(buffer.as_slice())
+        .map_err(|error| CalError::MonitoringDecode(error.into()))?;
+    Ok(logging.logs.into_iter().map(Into::into).collect())
+}
+
+/// Returns an iterator over log messages as they arrive, polling [`logs`] every
+/// `interval` while no message is pending.
+pub fn poll_logs(interval: Duration) -> LogPoller {
+    LogPoller {
+        pending: VecDeque::new(),
+        interval,
+        done: false,
+    }
+}
+
+/// Blocking iterator over new log messages, see [`poll_logs`].
+///
+/// The iterator ends once eCAL is finalized, or after yielding the error if the
+/// messages cannot be read.
+#[derive(Debug)]
+pub struct LogPoller {
+    pending: VecDeque<LogRecord>,
+    interval: Duration,
+    done: bool,
+}
+
+impl LogPoller {
+    /// Returns the next message if one has arrived, without waiting.
+    pub fn try_next(&mut self) -> Result<Option<LogRecord>> {
+        if self.pending.is_empty() {
+            self.pending.extend(logs()?);
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+impl Iterator for LogPoller {
+    type Item = Result<LogRecord>;
+
+    fn next(&mut self) -> Option<Result<LogRecord>> {
+        while !self.done {
+            match self.try_next() {
+                Ok(Some(record)) => return Some(Ok(record)),
+                Ok(None) if crate::ok() => thread::sleep(self.interval),
+                Ok(None) => self.done = true,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl From<pb::Monitoring> for Snapshot {
+    fn from(monitoring: pb::Monitoring) -> Self {
+        let (publishers, subscribers) = monitoring
+            .topics
+            .into_iter()
+            .filter(|topic| topic.direction == "publisher" || topic.direction == "subscriber")
+            .partition::<Vec<_>, _>(|topic| topic.direction == "publisher");
+        Snapshot {
+            hosts: monitoring.hosts.into_iter().map(Into::into).collect(),
+            processes: monitoring.processes.into_iter().map(Into::into).collect(),
+            publishers: publishers.into_iter().map(Into::into).collect(),
+            subscribers: subscribers.into_iter().map(Into::into).collect(),
+            services: monitoring.services.into_iter().map(Into::into).collect(),
+            clients: monitoring.clients.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<pb::Host> for Host {
+    fn from(host: pb::Host) -> Self {
+        Host {
+            name: host.hname,
+            os: host.os.map(|os| os.osname).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<pb::Process> for Process {
+    fn from(process: pb::Process) -> Self {
+        use pb::EProcessSeverity as Severity;
+        use pb::EProcessSeverityLevel as Level;
+        let state = process.state.unwrap_or_default();
+        Process {
+            host: process.hname,
+            pid: process.pid,
+            name: process.pname,
+            unit: process.uname,
+            params: process.pparam,
+            state: match Severity::from_i32(state.severity) {
+                Some(Severity::ProcSevHealthy) => NodeState::Healthy,
+                Some(Severity::ProcSevWarning) => NodeState::Warning,
+                Some(Severity::ProcSevCritical) => NodeState::Critical,
+                Some(Severity::ProcSevFailed) => NodeState::Failed,
+                Some(Severity::ProcSevUnknown) | None => NodeState::Unknown,
+            },
+            severity_level: match Level::from_i32(state.severity_level) {
+                Some(Level::ProcSevLevel1) => Some(SeverityLevel::Level1),
+                Some(Level::ProcSevLevel2) => Some(SeverityLevel::Level2),
+                Some(Level::ProcSevLevel3) => Some(SeverityLevel::Level3),
+                Some(Level::ProcSevLevel4) => Some(SeverityLevel::Level4),
+                Some(Level::ProcSevLevel5) => Some(SeverityLevel::Level5),
+                Some(Level::ProcSevLevelUnknown) | None => None,
+            },
+            state_info: state.info,
+            ecal_version: process.ecal_runtime_version,
+            registration_clock: process.rclock,
+        }
+    }
+}
+
+impl From<pb::Topic> for Topic {
+    fn from(topic: pb::Topic) -> Self {
+        use pb::ETLayerType::*;
+        let layers = topic
+            .tlayer
+            .iter()
+            .filter(|layer| layer.confirmed)
+            .filter_map(|layer| match pb::ETLayerType::from_i32(layer.r#type)? {
+                TlEcalUdpMc => Some(TransportLayer::UdpMulticast),
+                TlEcalShm => Some(TransportLayer::Shm),
+                TlInproc => Some(TransportLayer::Inproc),
+                TlAll => Some(TransportLayer::All),
+                TlNone => None,
+            })
+            .collect();
+        Topic {
+            host: topic.hname,
+            pid: topic.pid,
+            process: topic.pname,
+            unit: topic.uname,
+            id: topic.tid,
+            name: topic.tname,
+            topic_type: topic.ttype,
+            description: topic.tdesc,
+            size: topic.tsize.max(0) as usize,
+            layers,
+            local_connections: topic.connections_loc.max(0) as usize,
+            external_connections: topic.connections_ext.max(0) as usize,
+            message_drops: topic.message_drops.max(0) as u64,
+            data_id: topic.did,
+            data_clock: topic.dclock,
+            // eCAL reports the frequency in mHz.
+            data_frequency: f64::from(topic.dfreq) / 1000.0,
+            registration_clock: topic.rclock,
+        }
+    }
+}
+
+impl From<pb::Service> for Service {
+    fn from(service: pb::Service) -> Self {
+        Service {
+            host: service.hname,
+            pid: service.pid,
+            process: service.pname,
+            unit: service.uname,
+            id: service.sid,
+            name: service.sname,
+            tcp_port: u16::try_from(service.tcp_port)
+                .ok()
+                .filter(|port| *port != 0),
+            methods: service.methods.into_iter().map(Into::into).collect(),
+            registration_clock: service.rclock,
+        }
+    }
+}
+
+impl From<pb::Method> for Method {
+    fn from(method: pb::Method) -> Self {
+        Method {
+            name: method.mname,
+            request_type: method.req_type,
+            response_type: method.resp_type,
+            call_count: method.call_count,
+        }
+    }
+}
+
+impl From<pb::Client> for Client {
+    fn from(client: pb::Client) -> Self {
+        Client {
+            host: client.hname,
+            pid: client.pid,
+            process: client.pname,
+            unit: client.uname,
+            id: client.sid,
+            service: client.sname,
+            registration_clock: client.rclock,
+        }
+    }
+}
+
+impl From<pb::LogMessage> for LogRecord {
+    fn from(message: pb::LogMessage) -> Self {
+        LogRecord {
+            // eCAL stamps messages with microseconds since the epoch.
+            time: UNIX_EPOCH + Duration::from_micros(message.time.max(0) as u64),
+            host: message.hname,
+            process: message.pname,
+            pid: message.pid,
+            unit: message.uname,
+            level: LogLevel::from_raw(message.level),
+            content: message.content,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pb, LogRecord, Snapshot};
+    use crate::{sys, LogLevel, NodeState, SeverityLevel, TransportLayer};
+    use prost::Message;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn topic(name: &str, direction: &str) -> pb::Topic {
+        pb::Topic {
+            tname: name.to_owned(),
+            direction: direction.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_monitoring() {
+        let mut publisher = topic("/ping", "publisher");
+        publisher.ttype = "proto:ecal_rs.Ping".to_owned();
+        publisher.dfreq = 10_500;
+        publisher.tlayer = vec![
+            pb::TLayer {
+                r#type: pb::ETLayerType::TlEcalShm as i32,
+                version: 1,
+                confirmed: true,
+            },
+            pb::TLayer {
+                r#type: pb::ETLayerType::TlEcalUdpMc as i32,
+                version: 1,
+                confirmed: false,
+            },
+        ];
+        let monitoring = pb::Monitoring {
+            processes: vec![pb::Process {
+                pname: "ping".to_owned(),
+                state: Some(pb::ProcessState {
+                    severity: pb::EProcessSeverity::ProcSevWarning as i32,
+                    info: "slow".to_owned(),
+                    severity_level: pb::EProcessSeverityLevel::ProcSevLevel2 as i32,
+                }),
+                ..Default::default()
+            }],
+            topics: vec![publisher, topic("/pong", "subscriber")],
+            ..Default::default()
+        };
+
+        let encoded = monitoring.encode_to_vec();
+        let snapshot = Snapshot::from(pb::Monitoring::decode(&encoded[..]).unwrap());
+
+        assert_eq!(snapshot.processes[0].state, NodeState::Warning);
+        assert_eq!(
+            snapshot.processes[0].severity_level,
+            Some(SeverityLevel::Level2)
+        );
+        assert_eq!(snapshot.publishers.len(), 1);
+        assert_eq!(snapshot.publishers[0].topic_type, "proto:ecal_rs.Ping");
+        assert_eq!(snapshot.publishers[0].layers, vec![TransportLayer::Shm]);
+        assert_eq!(snapshot.publishers[0].data_frequency, 10.5);
+        assert_eq!(snapshot.subscribers[0].name, "/pong");
+    }
+
+    #[test]
+    fn invalid_tcp_ports_are_dropped() {
+        let port = |tcp_port| {
+            super::Service::from(pb::Service {
+                tcp_port,
+                ..Default::default()
+            })
+            .tcp_port
+        };
+        assert_eq!(port(5000), Some(5000));
+        assert_eq!(port(0), None);
+        assert_eq!(port(-1), None);
+        assert_eq!(port(70_000), None);
+    }
+
+    #[test]
+    fn decodes_log_message() {
+        let message = pb::LogMessage {
+            time: 1_500_000,
+            hname: "host".to_owned(),
+            pid: 42,
+            pname: "ping".to_owned(),
+            uname: "ping_unit".to_owned(),
+            level: sys::eCAL_Logging_eLogLevel::log_level_debug2 as i32,
+            content: "sent".to_owned(),
+        };
+
+        let record = LogRecord::from(message);
+
+        assert_eq!(record.time, UNIX_EPOCH + Duration::from_millis(1500));
+        assert_eq!(record.level, Some(LogLevel::Debug2));
+        assert_eq!(record.unit, "ping_unit");
+    }
+}
@@ -0,0 +1,458 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+use std::{
+    ffi::CString,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use thiserror::Error;
+
+use crate::{sys, CalError, Result};
+
+/// The filter last applied through [`MonitoringFilter::apply`]. eCAL cannot be
+/// asked for its filter, so this is what guards restore.
+static APPLIED: Mutex<MonitoringFilter> = Mutex::new(MonitoringFilter {
+    include: None,
+    exclude: None,
+});
+
+/// Regular expressions selecting the topics the monitoring layer reports.
+///
+/// Filtering is switched on as soon as one of the patterns is set. The default
+/// filter has no patterns and reports all topics.
+///
+/// eCAL compiles the patterns with C++'s `std::regex` in its default ECMAScript
+/// grammar, so that is the dialect to write them in. Lookaheads like `(?!…)` are
+/// supported, inline flags like `(?i)`, lookbehinds, named groups and Unicode
+/// classes like `\p{L}` are not.
+///
+/// ```no_run
+/// # fn main() -> ecal::Result<()> {
+/// use ecal::monitoring::MonitoringFilter;
+///
+/// let _filter = MonitoringFilter::new()
+///     .include("^/kpns/")
+///     .exclude("debug$")
+///     .scoped()?;
+/// let snapshot = ecal::monitoring::snapshot()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MonitoringFilter {
+    include: Option<String>,
+    exclude: Option<String>,
+}
+
+impl MonitoringFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only reports topics whose name matches `pattern`.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include = Some(pattern.to_owned());
+        self
+    }
+
+    /// Does not report topics whose name matches `pattern`.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude = Some(pattern.to_owned());
+        self
+    }
+
+    /// The filter last applied through this API, the default if there was none.
+    ///
+    /// eCAL cannot be asked for its filter, so a filter configured in `ecal.ini`
+    /// is not reported here.
+    pub fn current() -> Self {
+        applied().clone()
+    }
+
+    /// Checks that both patterns are valid ECMAScript regular expressions.
+    ///
+    /// This follows the ECMAScript grammar without its legacy extensions: escaping
+    /// a letter or digit that has no meaning in ECMAScript, a quantifier without
+    /// anything to repeat and a `{` not starting a valid repetition are errors,
+    /// as are inline flags, lookbehinds, named groups and Unicode classes.
+    pub fn validate(&self) -> Result<()> {
+        for pattern in self.include.iter().chain(&self.exclude) {
+            if let Err(error) = check_ecmascript(pattern) {
+                return Err(CalError::InvalidFilter {
+                    pattern: pattern.clone(),
+                    error: error.into(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the patterns and hands them to eCAL.
+    ///
+    /// Nothing is changed if a pattern is invalid. If eCAL rejects one of the
+    /// settings, the previously applied filter is restored. Should restoring it
+    /// fail as well, [`CalError::FilterRestoreFailed`] is returned and the
+    /// filter eCAL uses is unknown until another one is applied.
+    ///
+    /// eCAL takes the exclude pattern, the include pattern and the filter state
+    /// one after the other, so its monitoring thread may briefly use a mix of
+    /// the old and the new filter.
+    pub fn apply(&self) -> Result<()> {
+        self.apply_to(&mut applied())
+    }
+
+    /// Applies the filter until the returned guard is dropped, which restores
+    /// the [`current`](#method.current) filter.
+    ///
+    /// If no filter was applied through this API before, the guard restores the
+    /// default filter, which also switches off a filter configured in `ecal.ini`.
+    pub fn scoped(&self) -> Result<FilterGuard> {
+        let mut applied = applied();
+        let previous = applied.clone();
+        self.apply_to(&mut applied)?;
+        Ok(FilterGuard { previous })
+    }
+
+    fn apply_to(&self, applied: &mut MonitoringFilter) -> Result<()> {
+        self.validate()?;
+        let include = CString::new(self.include.as_deref().unwrap_or_default())?;
+        let exclude = CString::new(self.exclude.as_deref().unwrap_or_default())?;
+        let previous_include = CString::new(applied.include.as_deref().unwrap_or_default())?;
+        let previous_exclude = CString::new(applied.exclude.as_deref().unwrap_or_default())?;
+
+        if let Err(setting) = set(&include, &exclude, self.is_enabled()) {
+            if let Err(restoring) = set(&previous_include, &previous_exclude, applied.is_enabled())
+            {
+                return Err(CalError::FilterRestoreFailed { setting, restoring });
+            }
+            return Err(CalError::SettingRejected(setting));
+        }
+        *applied = self.clone();
+        Ok(())
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.include.is_some() || self.exclude.is_some()
+    }
+}
+
+fn applied() -> MutexGuard<'static, MonitoringFilter> {
+    APPLIED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Syntax error in a filter pattern, reported as the source of [`CalError::InvalidFilter`].
+#[derive(Debug, Error)]
+#[error("{reason} at offset {offset}")]
+struct PatternError {
+    /// Character offset into the pattern.
+    offset: usize,
+    reason: &'static str,
+}
+
+fn error(offset: usize, reason: &'static str) -> PatternError {
+    PatternError { offset, reason }
+}
+
+/// Checks `pattern` against the ECMAScript grammar of `std::regex`.
+fn check_ecmascript(pattern: &str) -> std::result::Result<(), PatternError> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut pos = 0;
+    let mut open = Vec::new();
+    let mut repeatable = false;
+    while let Some(&c) = chars.get(pos) {
+        pos += 1;
+        repeatable = match c {
+            '\\' => escape(&chars, &mut pos, false)?,
+            '(' => {
+                if chars.get(pos) == Some(&'?') {
+                    match (chars.get(pos + 1), chars.get(pos + 2)) {
+                        (Some(':'), _) | (Some('='), _) | (Some('!'), _) => pos += 2,
+                        (Some('<'), Some('=')) | (Some('<'), Some('!')) => {
+                            return Err(error(pos - 1, "lookbehinds are not supported"))
+                        }
+                        (Some('<'), _) | (Some('P'), _) => {
+                            return Err(error(pos - 1, "named groups are not supported"))
+                        }
+                        _ => {
+                            return Err(error(
+                                pos - 1,
+                                "only `(?:`, `(?=` and `(?!` groups are supported",
+                            ))
+                        }
+                    }
+                }
+                open.push(pos - 1);
+                false
+            }
+            ')' => {
+                open.pop().ok_or_else(|| error(pos - 1, "unmatched `)`"))?;
+                true
+            }
+            '[' => {
+                class(&chars, &mut pos)?;
+                true
+            }
+            '*' | '+' | '?' | '{' => {
+                if !repeatable {
+                    return Err(error(pos - 1, "nothing to repeat"));
+                }
+                if c == '{' {
+                    repetition(&chars, &mut pos)?;
+                }
+                if chars.get(pos) == Some(&'?') {
+                    pos += 1;
+                }
+                false
+            }
+            '^' | '$' | '|' => false,
+            _ => true,
+        };
+    }
+    match open.pop() {
+        Some(offset) => Err(error(offset, "unclosed group")),
+        None => Ok(()),
+    }
+}
+
+/// Checks the escape after a backslash, returning whether it can be repeated.
+fn escape(
+    chars: &[char],
+    pos: &mut usize,
+    in_class: bool,
+) -> std::result::Result<bool, PatternError> {
+    let start = *pos - 1;
+    let c = *chars
+        .get(*pos)
+        .ok_or_else(|| error(start, "trailing backslash"))?;
+    *pos += 1;
+    let hex = |pos: &mut usize, digits: usize| {
+        let end = *pos + digits;
+        match chars.get(*pos..end) {
+            Some(digits) if digits.iter().all(char::is_ascii_hexdigit) => {
+                *pos = end;
+                Ok(true)
+            }
+            _ => Err(error(start, "invalid hexadecimal escape")),
+        }
+    };
+    match c {
+        'b' | 'B' if !in_class => Ok(false),
+        'b' | 'd' | 'D' | 's' | 'S' | 'w' | 'W' | 'f' | 'n' | 'r' | 't' | 'v' | '0' => Ok(true),
+        'c' => match chars.get(*pos) {
+            Some(letter) if letter.is_ascii_alphabetic() => {
+                *pos += 1;
+                Ok(true)
+            }
+            _ => Err(error(start, "invalid control escape")),
+        },
+        'x' => hex(pos, 2),
+        'u' => hex(pos, 4),
+        '1'..='9' if !in_class => {
+            while chars.get(*pos).is_some_and(char::is_ascii_digit) {
+                *pos += 1;
+            }
+            Ok(true)
+        }
+        'p' | 'P' => Err(error(start, "Unicode classes are not supported")),
+        'k' => Err(error(start, "named backreferences are not supported")),
+        c if c.is_alphanumeric() => Err(error(start, "unsupported escape")),
+        _ => Ok(true),
+    }
+}
+
+/// Checks a character class up to its closing `]`.
+fn class(chars: &[char], pos: &mut usize) -> std::result::Result<(), PatternError> {
+    let start = *pos - 1;
+    if chars.get(*pos) == Some(&'^') {
+        *pos += 1;
+    }
+    // Last plain character, which may start a range.
+    let mut previous = None;
+    loop {
+        let c = *chars
+            .get(*pos)
+            .ok_or_else(|| error(start, "unclosed character class"))?;
+        *pos += 1;
+        previous = match (c, previous, chars.get(*pos)) {
+            (']', ..) => return Ok(()),
+            ('\\', ..) => {
+                escape(chars, pos, true)?;
+                None
+            }
+            ('-', Some(low), Some(&high)) if high != ']' && high != '\\' => {
+                if high < low {
+                    return Err(error(*pos - 2, "invalid range"));
+                }
+                *pos += 1;
+                None
+            }
+            (c, ..) => Some(c),
+        };
+    }
+}
+
+/// Checks the bounds of a `{n}`, `{n,}` or `{n,m}` repetition after its `{`.
+fn repetition(chars: &[char], pos: &mut usize) -> std::result::Result<(), PatternError> {
+    let start = *pos - 1;
+    let number = |pos: &mut usize| {
+        let digits: String = chars[*pos..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        *pos += digits.len();
+        digits.parse::<u64>().ok()
+    };
+    let min = number(pos).ok_or_else(|| error(start, "invalid repetition"))?;
+    let max = if chars.get(*pos) == Some(&',') {
+        *pos += 1;
+        number(pos)
+    } else {
+        Some(min)
+    };
+    if chars.get(*pos) != Some(&'}') {
+        return Err(error(start, "invalid repetition"));
+    }
+    *pos += 1;
+    match max {
+        Some(max) if max < min => Err(error(start, "invalid repetition bounds")),
+        _ => Ok(()),
+    }
+}
+
+/// Hands the filter to eCAL, returning the name of the setting it rejected.
+fn set(
+    include: &CString,
+    exclude: &CString,
+    enabled: bool,
+) -> std::result::Result<(), &'static str> {
+    unsafe {
+        if sys::eCAL_Monitoring_SetExclFilter(exclude.as_ptr()) != 0 {
+            return Err("monitoring exclude filter");
+        }
+        if sys::eCAL_Monitoring_SetInclFilter(include.as_ptr()) != 0 {
+            return Err("monitoring include filter");
+        }
+        if sys::eCAL_Monitoring_SetFilterState(enabled as _) != 0 {
+            return Err("monitoring filter state");
+        }
+    }
+    Ok(())
+}
+
+/// Restores the previous [`MonitoringFilter`] when dropped.
+#[must_use = "the previous filter is restored as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct FilterGuard {
+    previous: MonitoringFilter,
+}
+
+impl Drop for FilterGuard {
+    fn drop(&mut self) {
+        if let Err(error) = self.previous.apply() {
+            log::warn!(
+                "Unable to restore the previous monitoring filter: {}",
+                error
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MonitoringFilter;
+    use crate::CalError;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    /// Serializes the tests changing the process wide filter.
+    static APPLYING: Mutex<()> = Mutex::new(());
+
+    fn applying() -> MutexGuard<'static, ()> {
+        APPLYING.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected() {
+        let _applying = applying();
+        let filter = MonitoringFilter::new().include("^/kpns/").exclude("([");
+        match filter.apply() {
+            Err(CalError::InvalidFilter { pattern, .. }) => assert_eq!(pattern, "(["),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_ne!(MonitoringFilter::current(), filter);
+    }
+
+    #[test]
+    fn unsupported_constructs_are_rejected() {
+        let valid = [
+            "^/kpns/",
+            "debug$",
+            "^(?!/internal/).*",
+            "^(?=/kpns/)+ping",
+            "^/(?:ping|pong)[0-9a-f]{2,}\\.?$",
+            "[^(]\\(",
+            "[]a\\-z\\]]",
+            "\\x41\\u0042\\cJ\\d+?",
+        ];
+        for pattern in valid.iter() {
+            assert!(
+                MonitoringFilter::new().include(pattern).validate().is_ok(),
+                "{} should be accepted",
+                pattern
+            );
+        }
+
+        let invalid = [
+            "([",
+            "(?i)ping",
+            "\\p{L}+",
+            "(?<=/)ping",
+            "(?<name>ping)",
+            "(?P<name>ping)",
+            "(?<name>ping)\\k<name>",
+            "ping)",
+            "ping\\",
+            "*ping",
+            "+x",
+            "a**",
+            "a{3,1}",
+            "ping{",
+            "[z-a]",
+            "[\\q]",
+            "[\\p{L}]",
+        ];
+        for pattern in invalid.iter() {
+            assert!(
+                MonitoringFilter::new().exclude(pattern).validate().is_err(),
+                "{} should be rejected",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn guard_restores_previous_filter() {
+        let _applying = applying();
+        let outer = MonitoringFilter::new().exclude("^__");
+        outer.apply().unwrap();
+        {
+            let inner = MonitoringFilter::new().include("^/kpns/");
+            let _guard = inner.scoped().unwrap();
+            assert_eq!(MonitoringFilter::current(), inner);
+        }
+        assert_eq!(MonitoringFilter::current(), outer);
+    }
+}
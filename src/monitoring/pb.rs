@@ -0,0 +1,416 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+// Generated by prost-build from proto/ecal/pb, do not edit. The
+// `ecal-build/tests/monitoring_pb.rs` test regenerates it and fails on differences.
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OsInfo {
+    /// name
+    #[prost(string, tag = "1")]
+    pub osname: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Host {
+    /// host name
+    #[prost(string, tag = "1")]
+    pub hname: ::prost::alloc::string::String,
+    /// operating system details
+    #[prost(message, optional, tag = "2")]
+    pub os: ::core::option::Option<OsInfo>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogMessage {
+    /// time
+    #[prost(int64, tag = "1")]
+    pub time: i64,
+    /// host name
+    #[prost(string, tag = "2")]
+    pub hname: ::prost::alloc::string::String,
+    /// process id
+    #[prost(int32, tag = "3")]
+    pub pid: i32,
+    /// process name
+    #[prost(string, tag = "4")]
+    pub pname: ::prost::alloc::string::String,
+    /// unit name
+    #[prost(string, tag = "5")]
+    pub uname: ::prost::alloc::string::String,
+    /// message level
+    #[prost(int32, tag = "6")]
+    pub level: i32,
+    /// message content
+    #[prost(string, tag = "7")]
+    pub content: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Logging {
+    /// log messages
+    #[prost(message, repeated, tag = "1")]
+    pub logs: ::prost::alloc::vec::Vec<LogMessage>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProcessState {
+    /// severity
+    #[prost(enumeration = "EProcessSeverity", tag = "1")]
+    pub severity: i32,
+    /// info string
+    #[prost(string, tag = "2")]
+    pub info: ::prost::alloc::string::String,
+    /// severity level
+    #[prost(enumeration = "EProcessSeverityLevel", tag = "3")]
+    pub severity_level: i32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Process {
+    /// registration clock
+    #[prost(int32, tag = "1")]
+    pub rclock: i32,
+    /// host name
+    #[prost(string, tag = "2")]
+    pub hname: ::prost::alloc::string::String,
+    /// process id
+    #[prost(int32, tag = "3")]
+    pub pid: i32,
+    /// process name
+    #[prost(string, tag = "4")]
+    pub pname: ::prost::alloc::string::String,
+    /// unit name
+    #[prost(string, tag = "5")]
+    pub uname: ::prost::alloc::string::String,
+    /// process parameter
+    #[prost(string, tag = "6")]
+    pub pparam: ::prost::alloc::string::String,
+    /// process memory
+    #[prost(int64, tag = "7")]
+    pub pmemory: i64,
+    /// process cpu usage
+    #[prost(float, tag = "8")]
+    pub pcpu: f32,
+    /// process user time
+    #[prost(float, tag = "9")]
+    pub usrptime: f32,
+    /// data write bytes per sec
+    #[prost(int64, tag = "10")]
+    pub datawrite: i64,
+    /// data read bytes per sec
+    #[prost(int64, tag = "11")]
+    pub dataread: i64,
+    /// process state info
+    #[prost(message, optional, tag = "12")]
+    pub state: ::core::option::Option<ProcessState>,
+    /// loaded / runtime eCAL version of a component
+    #[prost(string, tag = "17")]
+    pub ecal_runtime_version: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum EProcessSeverity {
+    /// condition unknown
+    ProcSevUnknown = 0,
+    /// process healthy
+    ProcSevHealthy = 1,
+    /// process warning level
+    ProcSevWarning = 2,
+    /// process critical
+    ProcSevCritical = 3,
+    /// process failed
+    ProcSevFailed = 4,
+}
+impl EProcessSeverity {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            EProcessSeverity::ProcSevUnknown => "proc_sev_unknown",
+            EProcessSeverity::ProcSevHealthy => "proc_sev_healthy",
+            EProcessSeverity::ProcSevWarning => "proc_sev_warning",
+            EProcessSeverity::ProcSevCritical => "proc_sev_critical",
+            EProcessSeverity::ProcSevFailed => "proc_sev_failed",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "proc_sev_unknown" => Some(Self::ProcSevUnknown),
+            "proc_sev_healthy" => Some(Self::ProcSevHealthy),
+            "proc_sev_warning" => Some(Self::ProcSevWarning),
+            "proc_sev_critical" => Some(Self::ProcSevCritical),
+            "proc_sev_failed" => Some(Self::ProcSevFailed),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum EProcessSeverityLevel {
+    /// condition unknown
+    ProcSevLevelUnknown = 0,
+    /// default severity level 1
+    ProcSevLevel1 = 1,
+    /// severity level 2
+    ProcSevLevel2 = 2,
+    /// severity level 3
+    ProcSevLevel3 = 3,
+    /// severity level 4
+    ProcSevLevel4 = 4,
+    /// severity level 5
+    ProcSevLevel5 = 5,
+}
+impl EProcessSeverityLevel {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            EProcessSeverityLevel::ProcSevLevelUnknown => "proc_sev_level_unknown",
+            EProcessSeverityLevel::ProcSevLevel1 => "proc_sev_level1",
+            EProcessSeverityLevel::ProcSevLevel2 => "proc_sev_level2",
+            EProcessSeverityLevel::ProcSevLevel3 => "proc_sev_level3",
+            EProcessSeverityLevel::ProcSevLevel4 => "proc_sev_level4",
+            EProcessSeverityLevel::ProcSevLevel5 => "proc_sev_level5",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "proc_sev_level_unknown" => Some(Self::ProcSevLevelUnknown),
+            "proc_sev_level1" => Some(Self::ProcSevLevel1),
+            "proc_sev_level2" => Some(Self::ProcSevLevel2),
+            "proc_sev_level3" => Some(Self::ProcSevLevel3),
+            "proc_sev_level4" => Some(Self::ProcSevLevel4),
+            "proc_sev_level5" => Some(Self::ProcSevLevel5),
+            _ => None,
+        }
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Method {
+    /// method name
+    #[prost(string, tag = "1")]
+    pub mname: ::prost::alloc::string::String,
+    /// request  type
+    #[prost(string, tag = "2")]
+    pub req_type: ::prost::alloc::string::String,
+    /// response type
+    #[prost(string, tag = "3")]
+    pub resp_type: ::prost::alloc::string::String,
+    /// call counter
+    #[prost(int64, tag = "4")]
+    pub call_count: i64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Service {
+    /// registration clock
+    #[prost(int32, tag = "1")]
+    pub rclock: i32,
+    /// host name
+    #[prost(string, tag = "2")]
+    pub hname: ::prost::alloc::string::String,
+    /// process name
+    #[prost(string, tag = "3")]
+    pub pname: ::prost::alloc::string::String,
+    /// unit name
+    #[prost(string, tag = "4")]
+    pub uname: ::prost::alloc::string::String,
+    /// process id
+    #[prost(int32, tag = "5")]
+    pub pid: i32,
+    /// service name
+    #[prost(string, tag = "6")]
+    pub sname: ::prost::alloc::string::String,
+    /// the tcp port used for that service
+    #[prost(int32, tag = "7")]
+    pub tcp_port: i32,
+    /// list of methods
+    #[prost(message, repeated, tag = "8")]
+    pub methods: ::prost::alloc::vec::Vec<Method>,
+    /// service id
+    #[prost(string, tag = "9")]
+    pub sid: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Client {
+    /// registration clock
+    #[prost(int32, tag = "1")]
+    pub rclock: i32,
+    /// host name
+    #[prost(string, tag = "2")]
+    pub hname: ::prost::alloc::string::String,
+    /// process name
+    #[prost(string, tag = "3")]
+    pub pname: ::prost::alloc::string::String,
+    /// unit name
+    #[prost(string, tag = "4")]
+    pub uname: ::prost::alloc::string::String,
+    /// process id
+    #[prost(int32, tag = "5")]
+    pub pid: i32,
+    /// service name
+    #[prost(string, tag = "6")]
+    pub sname: ::prost::alloc::string::String,
+    /// service id
+    #[prost(string, tag = "7")]
+    pub sid: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TLayer {
+    /// transport layer type
+    #[prost(enumeration = "ETLayerType", tag = "1")]
+    pub r#type: i32,
+    /// transport layer version
+    #[prost(int32, tag = "2")]
+    pub version: i32,
+    /// transport layer used ?
+    #[prost(bool, tag = "3")]
+    pub confirmed: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Topic {
+    /// registration clock (heart beat)
+    #[prost(int32, tag = "1")]
+    pub rclock: i32,
+    /// host name
+    #[prost(string, tag = "2")]
+    pub hname: ::prost::alloc::string::String,
+    /// process id
+    #[prost(int32, tag = "3")]
+    pub pid: i32,
+    /// process name
+    #[prost(string, tag = "4")]
+    pub pname: ::prost::alloc::string::String,
+    /// unit name
+    #[prost(string, tag = "5")]
+    pub uname: ::prost::alloc::string::String,
+    /// topic id
+    #[prost(string, tag = "6")]
+    pub tid: ::prost::alloc::string::String,
+    /// topic name
+    #[prost(string, tag = "7")]
+    pub tname: ::prost::alloc::string::String,
+    /// direction (publisher, subscriber)
+    #[prost(string, tag = "8")]
+    pub direction: ::prost::alloc::string::String,
+    /// topic type (protocol type)
+    #[prost(string, tag = "9")]
+    pub ttype: ::prost::alloc::string::String,
+    /// topic description (protocol description)
+    #[prost(bytes = "vec", tag = "10")]
+    pub tdesc: ::prost::alloc::vec::Vec<u8>,
+    /// generic topic description
+    #[prost(map = "string, string", tag = "11")]
+    pub attr: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// active topic transport layers and it's specific parameter
+    #[prost(message, repeated, tag = "12")]
+    pub tlayer: ::prost::alloc::vec::Vec<TLayer>,
+    /// topic size
+    #[prost(int32, tag = "13")]
+    pub tsize: i32,
+    /// number of local connected entities
+    #[prost(int32, tag = "16")]
+    pub connections_loc: i32,
+    /// number of external connected entities
+    #[prost(int32, tag = "17")]
+    pub connections_ext: i32,
+    /// dropped messages
+    #[prost(int32, tag = "18")]
+    pub message_drops: i32,
+    /// data send id (publisher setid)
+    #[prost(int64, tag = "19")]
+    pub did: i64,
+    /// data clock (send / receive action)
+    #[prost(int64, tag = "20")]
+    pub dclock: i64,
+    /// data frequency (send / receive samples per second) \[mHz\]
+    #[prost(int32, tag = "21")]
+    pub dfreq: i32,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ETLayerType {
+    /// undefined
+    TlNone = 0,
+    /// ecal udp multicast
+    TlEcalUdpMc = 1,
+    /// ecal shared memory
+    TlEcalShm = 4,
+    /// inner process (inner process memory forwarding)
+    TlInproc = 42,
+    /// all layer
+    TlAll = 255,
+}
+impl ETLayerType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ETLayerType::TlNone => "tl_none",
+            ETLayerType::TlEcalUdpMc => "tl_ecal_udp_mc",
+            ETLayerType::TlEcalShm => "tl_ecal_shm",
+            ETLayerType::TlInproc => "tl_inproc",
+            ETLayerType::TlAll => "tl_all",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "tl_none" => Some(Self::TlNone),
+            "tl_ecal_udp_mc" => Some(Self::TlEcalUdpMc),
+            "tl_ecal_shm" => Some(Self::TlEcalShm),
+            "tl_inproc" => Some(Self::TlInproc),
+            "tl_all" => Some(Self::TlAll),
+            _ => None,
+        }
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Monitoring {
+    /// hosts
+    #[prost(message, repeated, tag = "1")]
+    pub hosts: ::prost::alloc::vec::Vec<Host>,
+    /// processes
+    #[prost(message, repeated, tag = "2")]
+    pub processes: ::prost::alloc::vec::Vec<Process>,
+    /// services
+    #[prost(message, repeated, tag = "3")]
+    pub services: ::prost::alloc::vec::Vec<Service>,
+    /// topics
+    #[prost(message, repeated, tag = "4")]
+    pub topics: ::prost::alloc::vec::Vec<Topic>,
+    /// clients
+    #[prost(message, repeated, tag = "5")]
+    pub clients: ::prost::alloc::vec::Vec<Client>,
+}
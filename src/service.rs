@@ -0,0 +1,880 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+use crate::{callback, format, sys, CalError, EcalBuffer, Result};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int, c_long, c_void},
+    slice,
+    sync::{mpsc, Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Failure of a service method, handed to the calling client.
+///
+/// `state` is reported as the method's return state. Zero is what eCAL services
+/// use for success, so it is reported as -1 instead. `message` is sent instead of
+/// a response.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Service method failed with state {state}: {message}")]
+pub struct ServiceError {
+    pub state: i32,
+    pub message: String,
+}
+
+impl ServiceError {
+    pub fn new(state: i32, message: impl Into<String>) -> Self {
+        ServiceError {
+            state,
+            message: message.into(),
+        }
+    }
+}
+
+type Handler = dyn Fn(&[u8]) -> Result<Vec<u8>, ServiceError> + Send + Sync;
+
+thread_local! {
+    /// Response of the last method call on this thread. eCAL copies the response
+    /// after the method callback returned but never frees it, so it has to outlive
+    /// the callback.
+    static RESPONSE: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// eCAL service server offering methods to [`Client`]s.
+pub struct Server {
+    raw: sys::ECAL_HANDLE,
+    methods: Mutex<HashMap<String, Box<Box<Handler>>>>,
+}
+
+impl Server {
+    pub fn new(service_name: &str) -> Result<Self> {
+        let c_service_name = CString::new(service_name)?;
+        let raw = unsafe { sys::eCAL_Server_Create(c_service_name.as_ptr()) };
+        if raw.is_null() {
+            return Err(CalError::ServerCreationFailed(service_name.to_string()));
+        }
+        Ok(Server {
+            raw,
+            methods: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn service_name(&self) -> Option<String> {
+        EcalBuffer::alloc(|buf, len| unsafe { sys::eCAL_Server_GetServiceName(self.raw, buf, len) })
+            .map(|buffer| String::from_utf8_lossy(buffer.as_slice()).into_owned())
+    }
+
+    /// Offers `method`, decoding requests with `D` and encoding responses with `S`.
+    ///
+    /// The request and response types are registered as `D::topic_type()` and
+    /// `S::topic_type()`. The handler runs on an eCAL thread, possibly for several
+    /// requests at once. A method of the same name is replaced.
+    pub fn add_method<Req, Resp, D, S>(
+        &self,
+        method: &str,
+        handler: impl Fn(Req) -> Result<Resp, ServiceError> + Send + Sync + 'static,
+    ) -> Result<()>
+    where
+        D: format::Format + for<'a> format::Deserializer<'a, Req>,
+        S: format::Format + format::Serializer<Resp>,
+    {
+        self.add_method_with_types::<Req, Resp, D, S>(
+            method,
+            &D::topic_type(),
+            &S::topic_type(),
+            handler,
+        )
+    }
+
+    /// Same as [`add_method`](#method.add_method), registering the given request and
+    /// response type names, e.g. to match the names other eCAL implementations use.
+    pub fn add_method_with_types<Req, Resp, D, S>(
+        &self,
+        method: &str,
+        request_type: &str,
+        response_type: &str,
+        handler: impl Fn(Req) -> Result<Resp, ServiceError> + Send + Sync + 'static,
+    ) -> Result<()>
+    where
+        D: for<'a> format::Deserializer<'a, Req>,
+        S: format::Serializer<Resp>,
+    {
+        self.add_raw_method(method, request_type, response_type, move |request| {
+            let request = D::deserialize(request).map_err(|err| {
+                ServiceError::new(-1, format!("Unable to decode the request: {}", err))
+            })?;
+            let response = handler(request)?;
+            let mut buffer = Vec::with_capacity(S::encoded_len(&response).unwrap_or(32));
+            S::serialize(&response, &mut buffer).map_err(|err| {
+                ServiceError::new(-1, format!("Unable to encode the response: {}", err))
+            })?;
+            Ok(buffer)
+        })
+    }
+
+    /// Offers `method` with untyped request and response payloads.
+    pub fn add_raw_method(
+        &self,
+        method: &str,
+        request_type: &str,
+        response_type: &str,
+        handler: impl Fn(&[u8]) -> Result<Vec<u8>, ServiceError> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let c_method = CString::new(method)?;
+        let c_request_type = CString::new(request_type)?;
+        let c_response_type = CString::new(response_type)?;
+
+        let mut methods = self.lock();
+        self.remove(&mut methods, method)?;
+
+        let handler: Box<Box<Handler>> = Box::new(Box::new(handler));
+        let status = unsafe {
+            sys::eCAL_Server_AddMethodCallbackC(
+                self.raw,
+                c_method.as_ptr(),
+                c_request_type.as_ptr(),
+                c_response_type.as_ptr(),
+                Some(Self::method_trampoline),
+                &*handler as *const Box<Handler> as *mut c_void,
+            )
+        };
+        if status == 0 {
+            return Err(CalError::CallbackRegistrationFailed);
+        }
+        methods.insert(method.to_string(), handler);
+        Ok(())
+    }
+
+    /// Stops offering `method` and frees its handler.
+    pub fn remove_method(&self, method: &str) -> Result<()> {
+        let mut methods = self.lock();
+        self.remove(&mut methods, method)
+    }
+
+    fn remove(&self, methods: &mut HashMap<String, Box<Box<Handler>>>, method: &str) -> Result<()> {
+        if methods.contains_key(method) {
+            let c_method = CString::new(method)?;
+            unsafe { sys::eCAL_Server_RemMethodCallbackC(self.raw, c_method.as_ptr()) };
+            methods.remove(method);
+        }
+        Ok(())
+    }
+
+    unsafe extern "C" fn method_trampoline(
+        method: *const c_char,
+        _request_type: *const c_char,
+        _response_type: *const c_char,
+        request: *const c_char,
+        request_len: c_int,
+        response: *mut *mut c_void,
+        response_len: *mut c_int,
+        par: *mut c_void,
+    ) -> c_int {
+        let handler = &*(par as *const Box<Handler>);
+        let request = slice::from_raw_parts(request as *const u8, request_len.max(0) as usize);
+
+        let method = || CStr::from_ptr(method).to_string_lossy();
+        let (state, body) = match callback::catch_unwind("Service method", || handler(request)) {
+            Some(Ok(body)) => (0, body),
+            Some(Err(err)) => {
+                log::warn!("Service method `{}`: {}", method(), err);
+                let state = if err.state == 0 { -1 } else { err.state };
+                (state, err.message.into_bytes())
+            }
+            None => (
+                -1,
+                format!("Service method `{}` panicked", method()).into_bytes(),
+            ),
+        };
+
+        RESPONSE.with(|buffer| {
+            let mut buffer = buffer.borrow_mut();
+            *buffer = body;
+            *response = buffer.as_mut_ptr() as *mut c_void;
+            *response_len = buffer.len() as c_int;
+        });
+        state
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Box<Box<Handler>>>> {
+        lock(&self.methods)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        // Destroying the server stops all method calls, after which the handlers
+        // are freed together with the map.
+        unsafe {
+            sys::eCAL_Server_Destroy(self.raw);
+        }
+    }
+}
+
+unsafe impl Send for Server {}
+unsafe impl Sync for Server {}
+
+/// Outcome of a service call as reported by eCAL.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CallState {
+    None,
+    Executed,
+    Failed,
+}
+
+impl From<sys::eCallState> for CallState {
+    fn from(state: sys::eCallState) -> Self {
+        use sys::eCallState::*;
+        use CallState::*;
+        match state {
+            call_state_none => None,
+            call_state_executed => Executed,
+            call_state_failed => Failed,
+        }
+    }
+}
+
+/// Information about the server that answered a call.
+///
+/// eCAL does not report all of it for every kind of call, missing strings are empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInfo {
+    pub host_name: String,
+    pub service_name: String,
+    pub service_id: String,
+    pub method_name: String,
+    pub error_msg: String,
+    /// Return state of the method, zero on success.
+    pub ret_state: i32,
+    pub call_state: CallState,
+}
+
+impl ServiceInfo {
+    fn from_sys(info: &sys::SServiceInfoC) -> Self {
+        let string = |ptr: *const c_char| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(ptr) }
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+        ServiceInfo {
+            host_name: string(info.host_name),
+            service_name: string(info.service_name),
+            service_id: string(info.service_id),
+            method_name: string(info.method_name),
+            error_msg: string(info.error_msg),
+            ret_state: info.ret_state,
+            call_state: info.call_state.into(),
+        }
+    }
+}
+
+/// Response of a single server to a call.
+#[derive(Debug, Clone)]
+pub struct ServiceResponse<Resp> {
+    pub info: ServiceInfo,
+    /// The decoded response, or the [`ServiceError`] the method failed with.
+    pub response: Result<Resp, ServiceError>,
+}
+
+impl ServiceResponse<Vec<u8>> {
+    fn new(info: ServiceInfo, body: &[u8]) -> Self {
+        let response = match (info.call_state, info.ret_state) {
+            (CallState::Executed, 0) => Ok(body.to_vec()),
+            (CallState::Executed, state) => Err(ServiceError::new(
+                state,
+                String::from_utf8_lossy(body).into_owned(),
+            )),
+            (_, state) => Err(ServiceError::new(state, info.error_msg.clone())),
+        };
+        ServiceResponse { info, response }
+    }
+
+    fn decode<Resp, D>(self) -> ServiceResponse<Resp>
+    where
+        D: for<'a> format::Deserializer<'a, Resp>,
+    {
+        let response = self.response.and_then(|body| {
+            D::deserialize(&body).map_err(|err| {
+                ServiceError::new(-1, format!("Unable to decode the response: {}", err))
+            })
+        });
+        ServiceResponse {
+            info: self.info,
+            response,
+        }
+    }
+}
+
+type ResponseListener = Box<dyn FnMut(ServiceResponse<Vec<u8>>) + Send>;
+
+struct ClientHandle {
+    raw: sys::ECAL_HANDLE,
+    /// Serializes calls, as eCAL reports the responses of all calls through a
+    /// single callback.
+    calls: Mutex<()>,
+    /// Receives the responses of the call in flight.
+    current: Mutex<Option<ResponseListener>>,
+    /// Runs the non-blocking calls.
+    worker: Worker,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs jobs one after another on a thread started with the first of them.
+#[derive(Default)]
+struct Worker {
+    jobs: Mutex<Option<mpsc::Sender<Job>>>,
+}
+
+impl Worker {
+    /// Queues `job` behind the jobs not yet finished.
+    ///
+    /// The thread stops once the worker and all jobs queued on it are gone.
+    fn run(&self, job: Job) {
+        let mut jobs = lock(&self.jobs);
+        let jobs = jobs.get_or_insert_with(|| {
+            let (jobs, queue) = mpsc::channel::<Job>();
+            thread::spawn(move || {
+                for job in queue {
+                    callback::catch_unwind("Service call", job);
+                }
+            });
+            jobs
+        });
+        // The thread only stops after this sender is gone.
+        let _ = jobs.send(job);
+    }
+}
+
+impl ClientHandle {
+    unsafe extern "C" fn response_trampoline(
+        info: *const sys::SServiceInfoC,
+        response: *const c_char,
+        response_len: c_int,
+        par: *mut c_void,
+    ) {
+        let handle = &*(par as *const ClientHandle);
+        let body = if response.is_null() {
+            &[]
+        } else {
+            slice::from_raw_parts(response as *const u8, response_len.max(0) as usize)
+        };
+        callback::catch_unwind("Service response callback", || {
+            let response = ServiceResponse::new(ServiceInfo::from_sys(&*info), body);
+            match lock(&handle.current).as_mut() {
+                Some(listener) => listener(response),
+                None => log::warn!("Dropping a response outside of any call"),
+            }
+        });
+    }
+}
+
+impl ClientHandle {
+    /// Calls `method` on all servers, handing every response to `listener`.
+    ///
+    /// Blocks until all servers answered or `timeout` elapsed and returns whether
+    /// eCAL reported success.
+    fn call(
+        &self,
+        method: &CStr,
+        request: &[u8],
+        timeout: Duration,
+        listener: ResponseListener,
+    ) -> bool {
+        let _call = lock(&self.calls);
+        *lock(&self.current) = Some(listener);
+        // `eCAL_Client_Call` forwards to `CServiceClient::Call(method, request,
+        // timeout)`, which `ecal_client.h` documents as the "blocking call, using
+        // callback": it sends the request to every server and runs the response
+        // callback for each answer before it returns. The "none blocking" in the C
+        // header only means the responses are not returned but passed to the
+        // callback, so none arrive after it returned and `current` can be cleared.
+        let status = unsafe {
+            sys::eCAL_Client_Call(
+                self.raw,
+                method.as_ptr(),
+                request.as_ptr() as *const c_char,
+                request.len() as c_int,
+                timeout_ms(timeout),
+            )
+        };
+        *lock(&self.current) = None;
+        status != 0
+    }
+}
+
+/// Calls the contained closure when dropped.
+struct OnDrop<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Drop for OnDrop<F> {
+    fn drop(&mut self) {
+        if let Some(f) = self.0.take() {
+            f();
+        }
+    }
+}
+
+impl Drop for ClientHandle {
+    fn drop(&mut self) {
+        // Destroying the client stops the response callback.
+        unsafe {
+            sys::eCAL_Client_Destroy(self.raw);
+        }
+    }
+}
+
+unsafe impl Send for ClientHandle {}
+unsafe impl Sync for ClientHandle {}
+
+/// eCAL service client calling the methods of [`Server`]s.
+pub struct Client {
+    handle: Arc<ClientHandle>,
+}
+
+impl Client {
+    pub fn new(service_name: &str) -> Result<Self> {
+        let c_service_name = CString::new(service_name)?;
+        let raw = unsafe { sys::eCAL_Client_Create(c_service_name.as_ptr()) };
+        if raw.is_null() {
+            return Err(CalError::ClientCreationFailed(service_name.to_string()));
+        }
+        let handle = Arc::new(ClientHandle {
+            raw,
+            calls: Mutex::new(()),
+            current: Mutex::new(None),
+            worker: Worker::default(),
+        });
+
+        let status = unsafe {
+            sys::eCAL_Client_AddResponseCallbackC(
+                raw,
+                Some(ClientHandle::response_trampoline),
+                Arc::as_ptr(&handle) as *mut c_void,
+            )
+        };
+        if status == 0 {
+            return Err(CalError::CallbackRegistrationFailed);
+        }
+        Ok(Client { handle })
+    }
+
+    /// Only calls servers on `host_name`, or on all hosts if it is empty or `*`.
+    pub fn set_host_name(&mut self, host_name: &str) -> Result<()> {
+        let c_host_name = CString::new(host_name)?;
+        let status = unsafe { sys::eCAL_Client_SetHostName(self.handle.raw, c_host_name.as_ptr()) };
+        if status == 0 {
+            Err(CalError::SettingRejected("host_name"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calls `method`, encoding the request with `S` and decoding responses with `D`.
+    ///
+    /// Blocks until all servers answered or `timeout` elapsed and returns one
+    /// response per answering server. A server whose method failed is reported
+    /// with [`CallState::Failed`] among them. If eCAL reports the call as failed
+    /// and no server answered, for example because none is available or all
+    /// timed out, the call fails with [`CalError::CallFailed`].
+    pub fn call<Req, Resp, S, D>(
+        &self,
+        method: &str,
+        request: &Req,
+        timeout: Duration,
+    ) -> Result<Vec<ServiceResponse<Resp>>>
+    where
+        S: format::Serializer<Req>,
+        D: for<'a> format::Deserializer<'a, Resp>,
+    {
+        let responses = self.call_raw(method, &encode::<Req, S>(request)?, timeout)?;
+        Ok(responses
+            .into_iter()
+            .map(ServiceResponse::decode::<Resp, D>)
+            .collect())
+    }
+
+    /// Calls `method` with an untyped request, see [`call`](#method.call).
+    pub fn call_raw(
+        &self,
+        method: &str,
+        request: &[u8],
+        timeout: Duration,
+    ) -> Result<Vec<ServiceResponse<Vec<u8>>>> {
+        let c_method = CString::new(method)?;
+        // `eCAL_Client_Call_Wait` only reports the first server, so collect the
+        // responses of all servers from the response callback instead.
+        let responses = Arc::new(Mutex::new(Vec::new()));
+        let collect = responses.clone();
+        let succeeded = self.handle.call(
+            &c_method,
+            request,
+            timeout,
+            Box::new(move |response| lock(&collect).push(response)),
+        );
+        let responses = std::mem::take(&mut *lock(&responses));
+        if !succeeded && responses.is_empty() {
+            return Err(CalError::CallFailed(method.to_owned()));
+        }
+        Ok(responses)
+    }
+
+    /// Calls `method` on the client's background thread, handing every server's
+    /// response to `callback` as it arrives.
+    ///
+    /// Calls of the same client run one after another on a single thread, started
+    /// with the first call, as eCAL hands the responses of all of them to one
+    /// callback. A call only starts once the calls queued before it finished, so
+    /// `n` pending calls can take up to `n` times their timeout, and a blocking
+    /// [`call`](#method.call) waits for them as well. Calls on different
+    /// `Client`s do not wait for each other.
+    ///
+    /// `callback` runs while the call holds the client, so it must not make a
+    /// blocking call on the same `Client` or wait for one of its pending calls,
+    /// which deadlocks. Use another `Client` for nested calls.
+    pub fn call_with_callback<Req, Resp, S, D, F>(
+        &self,
+        method: &str,
+        request: &Req,
+        timeout: Duration,
+        mut callback: F,
+    ) -> Result<PendingCall>
+    where
+        S: format::Serializer<Req>,
+        D: for<'a> format::Deserializer<'a, Resp> + 'static,
+        Resp: 'static,
+        F: FnMut(ServiceResponse<Resp>) + Send + 'static,
+    {
+        self.spawn_call(
+            method,
+            encode::<Req, S>(request)?,
+            timeout,
+            Box::new(move |response| callback(response.decode::<Resp, D>())),
+            || {},
+        )
+    }
+
+    /// Same as [`call_with_callback`](#method.call_with_callback) with untyped payloads.
+    pub fn call_raw_with_callback<F>(
+        &self,
+        method: &str,
+        request: &[u8],
+        timeout: Duration,
+        callback: F,
+    ) -> Result<PendingCall>
+    where
+        F: FnMut(ServiceResponse<Vec<u8>>) + Send + 'static,
+    {
+        self.spawn_call(method, request.to_vec(), timeout, Box::new(callback), || {})
+    }
+
+    /// Calls `method` on the client's background thread, resolving to the
+    /// responses of all servers once the call finished.
+    ///
+    /// The call waits for the calls started before it on this client, see
+    /// [`call_with_callback`](#method.call_with_callback). Waiting for the future
+    /// in a response callback of the same `Client` deadlocks.
+    #[cfg(feature = "async")]
+    pub fn call_async<Req, Resp, S, D>(
+        &self,
+        method: &str,
+        request: &Req,
+        timeout: Duration,
+    ) -> Result<ResponseFuture<Resp>>
+    where
+        S: format::Serializer<Req>,
+        D: for<'a> format::Deserializer<'a, Resp> + 'static,
+        Resp: Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(FutureState {
+            responses: Vec::new(),
+            done: false,
+            waker: None,
+        }));
+        let collect = state.clone();
+        let finish = state.clone();
+        self.spawn_call(
+            method,
+            encode::<Req, S>(request)?,
+            timeout,
+            Box::new(move |response| {
+                lock(&collect).responses.push(response.decode::<Resp, D>());
+            }),
+            move || {
+                let mut state = lock(&finish);
+                state.done = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            },
+        )?;
+        Ok(ResponseFuture { state })
+    }
+
+    /// Queues the call on the client's worker, behind all calls not yet finished.
+    fn spawn_call(
+        &self,
+        method: &str,
+        request: Vec<u8>,
+        timeout: Duration,
+        listener: ResponseListener,
+        done: impl FnOnce() + Send + 'static,
+    ) -> Result<PendingCall> {
+        let c_method = CString::new(method)?;
+        let handle = self.handle.clone();
+        let (status, outcome) = mpsc::sync_channel(1);
+        self.handle.worker.run(Box::new(move || {
+            // Finishes the call even if a listener panicked.
+            let _done = OnDrop(Some(done));
+            let _ = status.send(handle.call(&c_method, &request, timeout, listener));
+        }));
+        Ok(PendingCall { outcome })
+    }
+}
+
+/// A call started by [`Client::call_with_callback`].
+///
+/// Dropping it lets the call finish in the background.
+pub struct PendingCall {
+    outcome: mpsc::Receiver<bool>,
+}
+
+impl PendingCall {
+    /// Blocks until the call finished, returning whether eCAL reported success.
+    pub fn wait(self) -> bool {
+        self.outcome.recv().unwrap_or(false)
+    }
+}
+
+/// Future returned by [`Client::call_async`].
+#[cfg(feature = "async")]
+pub struct ResponseFuture<Resp> {
+    state: Arc<Mutex<FutureState<Resp>>>,
+}
+
+#[cfg(feature = "async")]
+struct FutureState<Resp> {
+    responses: Vec<ServiceResponse<Resp>>,
+    done: bool,
+    waker: Option<std::task::Waker>,
+}
+
+#[cfg(feature = "async")]
+impl<Resp> std::future::Future for ResponseFuture<Resp> {
+    type Output = Vec<ServiceResponse<Resp>>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let mut state = lock(&self.state);
+        if state.done {
+            std::task::Poll::Ready(std::mem::take(&mut state.responses))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            std::task::Poll::Pending
+        }
+    }
+}
+
+fn encode<Req, S: format::Serializer<Req>>(request: &Req) -> Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(S::encoded_len(request).unwrap_or(32));
+    S::serialize(request, &mut buffer).map_err(CalError::Encode)?;
+    Ok(buffer)
+}
+
+fn timeout_ms(timeout: Duration) -> c_long {
+    c_long::try_from(timeout.as_millis()).unwrap_or(c_long::MAX)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        lock, CallState, Client, ClientHandle, Handler, OnDrop, Server, ServiceError, ServiceInfo,
+        ServiceResponse, Worker,
+    };
+    use crate::{sys, CalError};
+    use std::{
+        os::raw::{c_char, c_int, c_void},
+        ptr, slice,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    fn client_handle() -> ClientHandle {
+        ClientHandle {
+            raw: ptr::null_mut(),
+            calls: Mutex::new(()),
+            current: Mutex::new(None),
+            worker: Worker::default(),
+        }
+    }
+
+    fn call(handler: *const Box<Handler>, request: &[u8]) -> (c_int, Vec<u8>) {
+        let mut response = ptr::null_mut::<c_void>();
+        let mut response_len: c_int = 0;
+        let state = unsafe {
+            Server::method_trampoline(
+                b"echo\0".as_ptr() as *const c_char,
+                ptr::null(),
+                ptr::null(),
+                request.as_ptr() as *const c_char,
+                request.len() as c_int,
+                &mut response,
+                &mut response_len,
+                handler as *mut c_void,
+            )
+        };
+        // eCAL copies the response after the callback returned.
+        let response =
+            unsafe { slice::from_raw_parts(response as *const u8, response_len as usize) };
+        (state, response.to_vec())
+    }
+
+    #[test]
+    fn trampoline_hands_back_response() {
+        let handler: Box<Handler> = Box::new(|request| match request {
+            b"" => Err(ServiceError::new(3, "empty")),
+            request => Ok(request.iter().rev().copied().collect()),
+        });
+        assert_eq!(call(&handler, b"abc"), (0, b"cba".to_vec()));
+        assert_eq!(call(&handler, b""), (3, b"empty".to_vec()));
+    }
+
+    #[test]
+    fn zero_error_state_is_not_success() {
+        let handler: Box<Handler> = Box::new(|_| Err(ServiceError::new(0, "failed")));
+        assert_eq!(call(&handler, b"abc"), (-1, b"failed".to_vec()));
+    }
+
+    #[test]
+    fn panicking_method_fails_the_call() {
+        let handler: Box<Handler> = Box::new(|_| panic!("boom"));
+        let (state, response) = call(&handler, b"abc");
+        assert_eq!(state, -1);
+        assert_eq!(response, b"Service method `echo` panicked".to_vec());
+    }
+
+    #[test]
+    fn responses_carry_method_failures() {
+        let mut info = sys::SServiceInfoC {
+            call_state: sys::eCallState::call_state_executed,
+            ..Default::default()
+        };
+        let ok = ServiceResponse::new(ServiceInfo::from_sys(&info), b"pong");
+        assert_eq!(ok.info.host_name, "");
+        assert_eq!(ok.info.call_state, CallState::Executed);
+        assert_eq!(ok.response, Ok(b"pong".to_vec()));
+
+        info.ret_state = 3;
+        let failed = ServiceResponse::new(ServiceInfo::from_sys(&info), b"empty");
+        assert_eq!(failed.response, Err(ServiceError::new(3, "empty")));
+    }
+
+    #[test]
+    fn every_server_response_is_collected() {
+        let handle = client_handle();
+        let responses = Arc::new(Mutex::new(Vec::new()));
+        let collect = responses.clone();
+        *lock(&handle.current) = Some(Box::new(move |response: ServiceResponse<Vec<u8>>| {
+            lock(&collect).push(response)
+        }));
+
+        for host in &[&b"alpha\0"[..], &b"beta\0"[..]] {
+            let info = sys::SServiceInfoC {
+                host_name: host.as_ptr() as *const c_char,
+                call_state: sys::eCallState::call_state_executed,
+                ..Default::default()
+            };
+            unsafe {
+                ClientHandle::response_trampoline(
+                    &info,
+                    b"pong".as_ptr() as *const c_char,
+                    4,
+                    &handle as *const ClientHandle as *mut c_void,
+                );
+            }
+        }
+
+        let responses = lock(&responses);
+        let hosts: Vec<_> = responses
+            .iter()
+            .map(|r| r.info.host_name.as_str())
+            .collect();
+        assert_eq!(hosts, vec!["alpha", "beta"]);
+        assert_eq!(responses[1].response, Ok(b"pong".to_vec()));
+    }
+
+    #[test]
+    fn failed_call_without_responses_is_an_error() {
+        let client = Client {
+            handle: Arc::new(client_handle()),
+        };
+        match client.call_raw("echo", b"ping", Duration::from_millis(10)) {
+            Err(CalError::CallFailed(method)) => assert_eq!(method, "echo"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn panicking_call_finishes_and_worker_survives() {
+        let worker = Worker::default();
+        let done = Arc::new(AtomicBool::new(false));
+        let finished = done.clone();
+        worker.run(Box::new(move || {
+            let _done = OnDrop(Some(move || finished.store(true, Ordering::SeqCst)));
+            panic!("listener failed");
+        }));
+
+        let (ran, next) = mpsc::channel();
+        worker.run(Box::new(move || ran.send(()).unwrap()));
+        next.recv().unwrap();
+        assert!(done.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn calls_of_different_clients_do_not_wait_for_each_other() {
+        let (slow, fast) = (Worker::default(), Worker::default());
+        let (release, stalled) = mpsc::channel::<()>();
+        slow.run(Box::new(move || stalled.recv().unwrap()));
+        let (queued, slow_done) = mpsc::channel();
+        slow.run(Box::new(move || queued.send(()).unwrap()));
+
+        let (ran, fast_done) = mpsc::channel();
+        fast.run(Box::new(move || ran.send(()).unwrap()));
+        fast_done.recv_timeout(Duration::from_secs(5)).unwrap();
+        // The second call of the stalled client waits for the first one.
+        assert!(slow_done.try_recv().is_err());
+
+        release.send(()).unwrap();
+        slow_done.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
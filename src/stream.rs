@@ -0,0 +1,306 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Async delivery of received messages, see [`Subscriber::into_stream`].
+//!
+//! The stream is woken from eCAL's receive callback and does not depend on a
+//! particular runtime.
+//!
+//! [`Subscriber::into_stream`]: crate::Subscriber::into_stream
+
+use crate::{
+    channel::{Close, DropPolicy, Open, Queue},
+    ok, CallbackGuard, RawSubscriber, Received, RecvError,
+};
+use futures_core::Stream;
+use futures_timer::Delay;
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// Messages handed over from the receive callback to the stream.
+pub(crate) struct Inbox<T> {
+    queue: Queue<T>,
+    waiting: Mutex<Waiting>,
+}
+
+struct Waiting {
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl<T: Send + 'static> Inbox<T> {
+    /// Creates an inbox closed by `open`, which is [`OPEN`](crate::channel::OPEN)
+    /// outside of tests, so finalizing eCAL ends the stream.
+    pub fn new(capacity: NonZeroUsize, policy: DropPolicy, open: &Open) -> Arc<Self> {
+        let inbox = Arc::new(Inbox {
+            queue: Queue::new(capacity, policy),
+            waiting: Mutex::new(Waiting {
+                waker: None,
+                closed: false,
+            }),
+        });
+        open.register(&inbox);
+        inbox
+    }
+}
+
+impl<T> Inbox<T> {
+    pub fn push(&self, item: T) {
+        self.queue.push(item);
+        if let Some(waker) = self.lock().waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Pops the oldest item, or returns `None` once the inbox is closed and empty.
+    pub fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut waiting = self.lock();
+        if let Some(item) = self.queue.pop(None) {
+            return Poll::Ready(Some(item));
+        }
+        if waiting.closed {
+            return Poll::Ready(None);
+        }
+        waiting.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Waiting> {
+        self.waiting.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Send> Close for Inbox<T> {
+    fn close(&self) {
+        Inbox::close(self);
+    }
+}
+
+impl<T> Inbox<T> {
+    /// Stops accepting items, releases a blocked [`push`](Self::push) and wakes
+    /// the stream, which ends once the queued items were taken.
+    fn close(&self) {
+        self.queue.close();
+        let waker = {
+            let mut waiting = self.lock();
+            waiting.closed = true;
+            waiting.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Stream of the messages received by a [`Subscriber`](crate::Subscriber).
+///
+/// Owns the subscriber, which stops receiving when the stream is dropped, so the
+/// stream can be moved into a spawned task. Messages that fail to decode are
+/// logged and skipped. The stream ends once eCAL was finalized and all received
+/// messages were taken.
+pub struct SubscriberStream<T> {
+    inbox: Arc<Inbox<Received<T>>>,
+    _guard: CallbackGuard,
+    _subscriber: RawSubscriber,
+}
+
+impl<T> SubscriberStream<T> {
+    pub(crate) fn new(
+        inbox: Arc<Inbox<Received<T>>>,
+        guard: CallbackGuard,
+        subscriber: RawSubscriber,
+    ) -> Self {
+        SubscriberStream {
+            inbox,
+            _guard: guard,
+            _subscriber: subscriber,
+        }
+    }
+
+    /// Number of messages dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.inbox.dropped()
+    }
+
+    /// Waits at most `timeout` for the next message, failing with [`RecvError::Timeout`].
+    pub fn recv_timeout(&mut self, timeout: Duration) -> RecvTimeout<'_, T> {
+        RecvTimeout {
+            stream: self,
+            delay: Delay::new(timeout),
+        }
+    }
+}
+
+impl<T> Stream for SubscriberStream<T> {
+    type Item = Received<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.inbox.poll_pop(cx) {
+            Poll::Ready(item) => Poll::Ready(item),
+            Poll::Pending if !ok() => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for SubscriberStream<T> {
+    fn drop(&mut self) {
+        // Releases a callback blocked on a full inbox, so the guard can unregister it.
+        self.inbox.close();
+    }
+}
+
+/// Future returned by [`SubscriberStream::recv_timeout`].
+pub struct RecvTimeout<'a, T> {
+    stream: &'a mut SubscriberStream<T>,
+    delay: Delay,
+}
+
+impl<'a, T> Future for RecvTimeout<'a, T> {
+    type Output = Result<Received<T>, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut *self.stream).poll_next(cx) {
+            Poll::Ready(Some(item)) => return Poll::Ready(Ok(item)),
+            Poll::Ready(None) => return Poll::Ready(Err(RecvError::Closed)),
+            Poll::Pending => {}
+        }
+        Pin::new(&mut self.delay)
+            .poll(cx)
+            .map(|()| Err(RecvError::Timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Inbox;
+    use crate::{
+        channel::{tests::registered, DropPolicy, Open},
+        CallbackGuard, Received, SampleInfo,
+    };
+    use futures::{executor::block_on, future::poll_fn};
+    use std::{
+        num::NonZeroUsize,
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    /// Registry of the test inboxes, `finalize()` in other tests closes `channel::OPEN`.
+    static OPEN: Open = Open::new();
+
+    fn inbox(capacity: usize, policy: DropPolicy) -> Arc<Inbox<u32>> {
+        Inbox::new(NonZeroUsize::new(capacity).unwrap(), policy, &OPEN)
+    }
+
+    fn next(inbox: &Inbox<u32>) -> Option<u32> {
+        block_on(poll_fn(|cx| inbox.poll_pop(cx)))
+    }
+
+    #[test]
+    fn push_wakes_pending_poll() {
+        let inbox = inbox(4, DropPolicy::DropOldest);
+        let producer = {
+            let inbox = inbox.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                inbox.push(7);
+            })
+        };
+        assert_eq!(next(&inbox), Some(7));
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn full_inbox_drops_oldest() {
+        let inbox = inbox(2, DropPolicy::DropOldest);
+        (1..=3).for_each(|item| inbox.push(item));
+        assert_eq!(inbox.dropped(), 1);
+        assert_eq!(next(&inbox), Some(2));
+        assert_eq!(next(&inbox), Some(3));
+    }
+
+    #[test]
+    fn closing_ends_the_stream() {
+        let inbox = inbox(4, DropPolicy::DropOldest);
+        inbox.push(1);
+        let producer = {
+            let inbox = inbox.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                inbox.close();
+            })
+        };
+        assert_eq!(next(&inbox), Some(1));
+        assert_eq!(next(&inbox), None);
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn inboxes_are_registered_for_finalize() {
+        let inbox = inbox(4, DropPolicy::Block);
+        assert!(registered(&OPEN, &inbox));
+    }
+
+    #[test]
+    fn dropping_the_stream_releases_a_blocked_push() {
+        let received = |msg| Received {
+            msg,
+            info: SampleInfo {
+                time: 0,
+                clock: None,
+                id: None,
+                size: 0,
+            },
+        };
+        let inbox = Inbox::new(NonZeroUsize::new(1).unwrap(), DropPolicy::Block, &OPEN);
+        inbox.push(received(1));
+
+        // Stands in for the registry lock held while eCAL dispatches a message.
+        let listeners = Arc::new(Mutex::new(()));
+        let (parked, is_parked) = mpsc::channel();
+        let callback = {
+            let (inbox, listeners) = (inbox.clone(), listeners.clone());
+            thread::spawn(move || {
+                let _dispatch = listeners.lock().unwrap();
+                parked.send(()).unwrap();
+                inbox.push(received(2));
+            })
+        };
+        is_parked.recv().unwrap();
+
+        let guard = CallbackGuard::new(move || drop(listeners.lock().unwrap()));
+        let (dropped, is_dropped) = mpsc::channel();
+        thread::spawn(move || {
+            // The order in which dropping a `SubscriberStream` releases them.
+            inbox.close();
+            drop(guard);
+            dropped.send(()).unwrap();
+        });
+        is_dropped.recv_timeout(Duration::from_secs(5)).unwrap();
+        callback.join().unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+use crate::{callback, sys, CallbackGuard, EcalBuffer};
+use std::{
+    ffi::CString,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+use thiserror::Error;
+
+/// How a subscriber treats publishers registering a different topic type or description.
+///
+/// eCAL's connect events do not tell which publisher connected, so the type and
+/// description eCAL has registered for the topic are compared instead. With
+/// several publishers of different types on one topic, that is the registration
+/// of any of them, and the check only reliably tells a single publisher apart.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TypeCheck {
+    /// Types are not compared, as eCAL does by default.
+    #[default]
+    Off,
+    /// Mismatches are reported on connect, payloads are still delivered.
+    Report,
+    /// Mismatches are reported on connect and payloads are not delivered until a
+    /// matching publisher connects.
+    Refuse,
+}
+
+/// A connected publisher registered a topic type or description other than the subscriber.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub struct TypeMismatch {
+    pub topic_name: String,
+    pub expected_type: String,
+    pub actual_type: String,
+    /// Both sides registered a description and they differ.
+    pub description_differs: bool,
+}
+
+impl TypeMismatch {
+    /// Compares the subscribed type and description with a publisher's.
+    ///
+    /// Descriptions are only compared if both sides registered one.
+    fn between(
+        topic_name: &str,
+        expected_type: &str,
+        expected_description: &[u8],
+        actual_type: &str,
+        actual_description: &[u8],
+    ) -> Option<Self> {
+        let description_differs = !expected_description.is_empty()
+            && !actual_description.is_empty()
+            && expected_description != actual_description;
+        if actual_type == expected_type && !description_differs {
+            return None;
+        }
+        Some(TypeMismatch {
+            topic_name: topic_name.to_owned(),
+            expected_type: expected_type.to_owned(),
+            actual_type: actual_type.to_owned(),
+            description_differs,
+        })
+    }
+}
+
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.actual_type != self.expected_type {
+            write!(
+                f,
+                "Topic `{}` is published as `{}`, subscribed as `{}`.",
+                self.topic_name, self.actual_type, self.expected_type
+            )
+        } else {
+            write!(
+                f,
+                "Topic `{}` is published with another description of `{}` than subscribed.",
+                self.topic_name, self.actual_type
+            )
+        }
+    }
+}
+
+/// Compares the subscribed type with the publishers' on every connect.
+pub(crate) struct TypeChecker {
+    /// Subscriber handle, only used from its own connect events.
+    raw: sys::ECAL_HANDLE,
+    topic_name: CString,
+    expected_type: String,
+    expected_description: Vec<u8>,
+    mode: TypeCheck,
+    mismatch: Mutex<Option<TypeMismatch>>,
+    listeners: callback::Registry<TypeMismatch>,
+}
+
+unsafe impl Send for TypeChecker {}
+unsafe impl Sync for TypeChecker {}
+
+impl TypeChecker {
+    pub fn new(
+        raw: sys::ECAL_HANDLE,
+        topic_name: CString,
+        expected_type: String,
+        expected_description: Vec<u8>,
+        mode: TypeCheck,
+    ) -> Self {
+        TypeChecker {
+            raw,
+            topic_name,
+            expected_type,
+            expected_description,
+            mode,
+            mismatch: Mutex::new(None),
+            listeners: callback::Registry::new(),
+        }
+    }
+
+    /// Looks up the type registered for the topic and reports a mismatch, if any.
+    pub fn check(&self) {
+        let actual_type = EcalBuffer::alloc(|buf, len| unsafe {
+            sys::eCAL_Util_GetTypeName(self.topic_name.as_ptr(), buf, len)
+        });
+        let actual_description = EcalBuffer::alloc(|buf, len| unsafe {
+            sys::eCAL_Sub_GetDescription(self.raw, buf, len)
+        })
+        .or_else(|| {
+            EcalBuffer::alloc(|buf, len| unsafe {
+                sys::eCAL_Util_GetDescription(self.topic_name.as_ptr(), buf, len)
+            })
+        });
+
+        if let Some(actual_type) = actual_type {
+            self.update(
+                &String::from_utf8_lossy(actual_type.as_slice()),
+                actual_description
+                    .as_ref()
+                    .map_or(&[], EcalBuffer::as_slice),
+            );
+        }
+    }
+
+    /// Compares with the type and description of a connected publisher.
+    fn update(&self, actual_type: &str, actual_description: &[u8]) {
+        let mismatch = TypeMismatch::between(
+            &self.topic_name.to_string_lossy(),
+            &self.expected_type,
+            &self.expected_description,
+            actual_type,
+            actual_description,
+        );
+        if let Some(mismatch) = &mismatch {
+            log::warn!("{}", mismatch);
+            self.listeners.dispatch(mismatch);
+        }
+        *self.lock() = mismatch;
+    }
+
+    /// The mismatch payloads are currently refused for.
+    pub fn refused(&self) -> Option<TypeMismatch> {
+        match self.mode {
+            TypeCheck::Refuse => self.mismatch(),
+            TypeCheck::Off | TypeCheck::Report => None,
+        }
+    }
+
+    pub fn mismatch(&self) -> Option<TypeMismatch> {
+        self.lock().clone()
+    }
+
+    pub fn listen(self: &Arc<Self>, listener: callback::Listener<TypeMismatch>) -> CallbackGuard {
+        callback::listen(
+            self,
+            |checker| &checker.listeners,
+            |_| true,
+            |_| {},
+            listener,
+        )
+        .expect("installing type mismatch listeners cannot fail")
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<TypeMismatch>> {
+        self.mismatch.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TypeCheck, TypeChecker, TypeMismatch};
+    use std::{ffi::CString, ptr};
+
+    fn checker(mode: TypeCheck) -> TypeChecker {
+        TypeChecker::new(
+            ptr::null_mut(),
+            CString::new("/kpns/ping").unwrap(),
+            "proto:Ping".to_owned(),
+            b"ping".to_vec(),
+            mode,
+        )
+    }
+
+    #[test]
+    fn only_refuse_refuses_payloads() {
+        for &mode in &[TypeCheck::Off, TypeCheck::Report, TypeCheck::Refuse] {
+            let checker = checker(mode);
+            checker.update("proto:Pong", b"ping");
+            assert!(checker.mismatch().is_some());
+            assert_eq!(checker.refused().is_some(), mode == TypeCheck::Refuse);
+
+            checker.update("proto:Ping", b"ping");
+            assert_eq!(checker.mismatch(), None);
+            assert_eq!(checker.refused(), None);
+        }
+    }
+
+    #[test]
+    fn type_name_mismatch() {
+        let mismatch =
+            TypeMismatch::between("/ping", "proto:Ping", b"ping", "proto:Pong", b"ping").unwrap();
+        assert!(!mismatch.description_differs);
+        assert_eq!(
+            mismatch.to_string(),
+            "Topic `/ping` is published as `proto:Pong`, subscribed as `proto:Ping`."
+        );
+    }
+
+    #[test]
+    fn description_mismatch() {
+        let mismatch =
+            TypeMismatch::between("/ping", "proto:Ping", b"ping", "proto:Ping", b"pong").unwrap();
+        assert!(mismatch.description_differs);
+        assert_eq!(
+            mismatch.to_string(),
+            "Topic `/ping` is published with another description of `proto:Ping` than subscribed."
+        );
+    }
+
+    #[test]
+    fn empty_descriptions_are_not_compared() {
+        assert_eq!(
+            TypeMismatch::between("/ping", "proto:Ping", b"ping", "proto:Ping", b""),
+            None
+        );
+        assert_eq!(
+            TypeMismatch::between("/ping", "proto:Ping", b"", "proto:Ping", b"ping"),
+            None
+        );
+    }
+}
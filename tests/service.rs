@@ -0,0 +1,114 @@
+/********************************************************************************
+ * Copyright (c) 2024 Kopernikus Automotive
+ * 
+ * This program and the accompanying materials are made available under the
+ * terms of the Apache License, Version 2.0 which is available at
+ * https://www.apache.org/licenses/LICENSE-2.0.
+ * 
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+ * WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+ * License for the specific language governing permissions and limitations
+ * under the License.
+ * 
+ * SPDX-License-Identifier: Apache-2.0
+ ********************************************************************************/
+
+//! Checks that `#[ecal::service]` expands to code that compiles.
+
+#![cfg(all(feature = "derive", feature = "use_prost"))]
+
+use ecal::ServiceError;
+
+#[derive(Clone, PartialEq, prost::Message, ecal::Message)]
+pub struct AddRequest {
+    #[prost(int32, tag = "1")]
+    pub a: i32,
+    #[prost(int32, tag = "2")]
+    pub b: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message, ecal::Message)]
+pub struct AddResponse {
+    #[prost(int32, tag = "1")]
+    pub sum: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message, ecal::Message)]
+pub struct NegateRequest {
+    #[prost(int32, tag = "1")]
+    pub value: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message, ecal::Message)]
+pub struct NegateResponse {
+    #[prost(int32, tag = "1")]
+    pub value: i32,
+}
+
+#[ecal::service]
+pub trait Calculator {
+    #[method_name = "Add"]
+    fn add(&self, request: AddRequest) -> Result<AddResponse, ServiceError>;
+
+    #[method_name = "Negate"]
+    #[request_type = "NegateRequest"]
+    #[response_type = "NegateResponse"]
+    fn negate(&self, request: NegateRequest) -> Result<NegateResponse, ServiceError>;
+
+    fn reset(&self, request: NegateRequest) -> Result<NegateResponse, ServiceError>;
+}
+
+struct Calc;
+
+impl Calculator for Calc {
+    fn add(&self, request: AddRequest) -> Result<AddResponse, ServiceError> {
+        Ok(AddResponse {
+            sum: request.a + request.b,
+        })
+    }
+
+    fn negate(&self, request: NegateRequest) -> Result<NegateResponse, ServiceError> {
+        Ok(NegateResponse {
+            value: -request.value,
+        })
+    }
+
+    fn reset(&self, _: NegateRequest) -> Result<NegateResponse, ServiceError> {
+        Err(ServiceError::new(1, "not supported"))
+    }
+}
+
+#[test]
+fn multi_method_service_compiles() {
+    let _server: fn(&str, Calc) -> ecal::Result<CalculatorServer> = CalculatorServer::new::<Calc>;
+    let _client: fn(&str) -> ecal::Result<CalculatorClient> = CalculatorClient::new;
+    let _add = CalculatorClient::add;
+    let _negate = CalculatorClient::negate;
+    let _reset = CalculatorClient::reset;
+
+    let sum = Calc.add(AddRequest { a: 1, b: 2 }).unwrap();
+    assert_eq!(sum.sum, 3);
+}
+
+/// Stands in for a crate re-exporting `ecal`.
+mod facade {
+    pub use ecal::*;
+}
+
+#[ecal::service(crate = "crate::facade")]
+pub trait Negator {
+    fn negate(&self, request: NegateRequest) -> Result<NegateResponse, ServiceError>;
+}
+
+impl Negator for Calc {
+    fn negate(&self, request: NegateRequest) -> Result<NegateResponse, ServiceError> {
+        Calculator::negate(self, request)
+    }
+}
+
+#[test]
+fn crate_path_is_configurable() {
+    let _server: fn(&str, Calc) -> facade::Result<NegatorServer> = NegatorServer::new::<Calc>;
+    let _client: fn(&str) -> facade::Result<NegatorClient> = NegatorClient::new;
+}